@@ -1,10 +1,18 @@
+use std::{collections::HashMap, sync::{atomic::AtomicBool, Arc, Mutex}};
+use anyhow::{Result, bail};
 use betting::Bets;
 use db_map::DBMap;
-use crate::serialize_utils::BetOutcome;
+use log::warn;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use crate::{bot_db::BotDB, config::{config, Config}, serialize_utils::BetOutcome};
 
 pub struct BettingBot {
     pub bets: Bets,
     pub msg_map: DBMap<BetOutcome, u64>,
+    pub db: BotDB,
+    pub scheduler_running: AtomicBool,
+    /// one lock per bet, held while a change to the bet is checked and applied
+    bet_locks: Mutex<HashMap<u64, Arc<AsyncMutex<()>>>>,
 }
 
 impl BettingBot {
@@ -12,6 +20,70 @@ impl BettingBot {
         BettingBot { 
             bets: Bets::new("bets.db").unwrap(), 
             msg_map: DBMap::new("msg_map.db").unwrap(),
+            db: BotDB::new("bot.db").unwrap(),
+            scheduler_running: AtomicBool::new(false),
+            bet_locks: Mutex::new(HashMap::new()),
         }
     }
+
+    /// Waits for the other changes to the bet to be done, the bet is left alone until the guard is dropped.
+    /// The betting crate has no transactions and some changes abort and recreate the bet, they can't overlap
+    pub async fn guard_bet(&self, bet: u64) -> OwnedMutexGuard<()> {
+        let lock = self.bet_locks.lock().unwrap().entry(bet).or_default().clone();
+        lock.lock_owned().await
+    }
+
+    /// The settings of the guild, falling back on config.toml for the ones it didn't set
+    pub fn config(&self, guild: u64) -> Result<Config> {
+        let mut cfg = config.clone();
+        for (key, value) in self.db.settings(guild)? {
+            if let Err(why) = cfg.set(&key, &value) {
+                warn!(target: "betting-bot", "Ignoring setting {}={} of Guild {}: {}", key, value, guild, why);
+            }
+        }
+        Ok(cfg)
+    }
+
+    /// Adds `diff` to the balance of the user, or takes it if negative
+    pub fn adjust_balance(&self, server: u64, user: u64, diff: i64) -> Result<()> {
+        if diff >= 0 {
+            self.bets.deposit(server, user, diff as u64)?;
+        } else {
+            self.bets.withdraw(server, user, diff.unsigned_abs())?;
+        }
+        Ok(())
+    }
+
+    /// Applies all the balance changes or none of them. The losses are checked against the balances 
+    /// and taken first so the gains are only given once nothing can be refused anymore, 
+    /// if a change fails anyway the ones already applied are reverted
+    pub fn apply_diffs(&self, server: u64, diffs: &[(u64, i64)]) -> Result<()> {
+        let mut totals: Vec<(u64, i64)> = Vec::new();
+        for (user, diff) in diffs {
+            match totals.iter_mut().find(|(other, _)| other == user) {
+                Some((_, total)) => *total += diff,
+                None => totals.push((*user, *diff)),
+            }
+        }
+        for (user, diff) in totals.iter().filter(|(_, diff)| *diff < 0) {
+            let balance = self.bets.balance(server, *user)?;
+            if balance < diff.unsigned_abs() {
+                bail!("<@{}> only has {} of the {} coins to take", user, balance, diff.unsigned_abs());
+            }
+        }
+        totals.sort_by_key(|(_, diff)| *diff);
+        let mut applied: Vec<(u64, i64)> = Vec::new();
+        for (user, diff) in totals.into_iter().filter(|(_, diff)| *diff != 0) {
+            if let Err(why) = self.adjust_balance(server, user, diff) {
+                for (user, diff) in applied.into_iter().rev() {
+                    if let Err(revert_why) = self.adjust_balance(server, user, -diff) {
+                        warn!(target: "betting-bot", "Couldn't revert {} on the balance of {} in Guild {}: {}", diff, user, server, revert_why);
+                    }
+                }
+                return Err(why);
+            }
+            applied.push((user, diff));
+        }
+        Ok(())
+    }
 }
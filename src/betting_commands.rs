@@ -1,32 +1,57 @@
 use anyhow::{Result, bail, Ok, anyhow};
+use betting::{Bet, utils::lrm};
 use chrono::prelude::*;
 use itertools::Itertools;
+use log::warn;
 use serenity::{
     all::{
-        CommandInteraction, CommandOptionType, CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateModal, EditMessage
+        CommandInteraction, CommandOptionType, CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateInputText, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateModal, CreateThread, EditMessage, EditThread, Message
     }, 
-    http::Http, model::{
+    http::{Http, HttpError}, model::{
         application::{
-            ActionRowComponent, ButtonStyle, ComponentInteraction, 
-            InputTextStyle, ModalInteraction
+            ActionRowComponent, ButtonStyle, ComponentInteraction, ComponentInteractionDataKind, 
+            InputTextStyle, Interaction, ModalInteraction
         }, 
-        prelude::{CommandDataOptionValue, GuildId}
+        prelude::{ChannelId, CommandDataOptionValue, GuildId, Member, Permissions, User}
     }, prelude::*
 };
 use shellwords::split;
-use crate::{betting_bot::BettingBot, config::config, serialize_utils::{BetOutcome, BetAction}, front_utils::{shorten, outcomes_display, bet_stub}};
+use std::cmp::{max, min};
+use crate::{betting_bot::BettingBot, config::{Config, SETTINGS}, serialize_utils::{BetOutcome, BetAction}, front_utils::{shorten, challenge_display, rake_display, parlays_display, parlay_display, outcomes_display, guesses_display, bet_stub, leaderboard_display, ranking, season_display, history_display, user_stats_display, guild_stats_display, config_display, audit_display}, stats::UserStats, payouts::{bankroll_covers, fixed_odds_payouts, guess_split, odds_capacity, outcome_shares, parlay_payout, pool_odds, split_pool, take_rake, GuessPayout, Settlement, Wager, GUESS_PAYOUTS}, bot_db::{AuditEntry, BetStatus, Bookmaker, Challenge, ChallengeStatus, ParlayStatus, Resolution, Standing, StakeLimits}};
+
+const PAGE_SIZE: usize = 10;
+const DAY: i64 = 24*60*60;
+/// custom id of the guess input of the BetOrder modal
+const GUESS_INPUT: &str = "guess";
+/// minutes the opponent of a challenge has to accept it by default
+const CHALLENGE_EXPIRY: i64 = 60;
+/// custom ids of the inputs of the bet creation form
+const DESC_INPUT: &str = "desc";
+const OUTCOME_INPUTS: [&str; 3] = ["outcome_1", "outcome_2", "outcome_3"];
+const MORE_OUTCOMES_INPUT: &str = "more_outcomes";
+/// keeps the bet message and its select menus within Discord limits
+const MAX_DESC_LEN: usize = 1500;
+const MAX_OUTCOME_LEN: usize = 200;
+const MAX_OUTCOMES: usize = 25;
+/// a compact bet has to fit in a message of 2000 characters, once resolved and with its numbers grown
+const COMPACT_MAX_LEN: usize = 1800;
 
 impl BettingBot {
     pub async fn account_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
         let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
         let user_uuid = command.user.id.get();
-        self.bets.create_account(server_uuid, user_uuid, config.starting_coins as u64)?;
+        let cfg = self.config(server_uuid)?;
+        self.bets.create_account(server_uuid, user_uuid, cfg.starting_coins as u64)?;
         let account: betting::AccountStatus = self.bets.account(server_uuid, user_uuid)?;
+        let bankruptcies = self.db.bankruptcy(server_uuid, user_uuid)?.map(|(count, _)| count).unwrap_or(0);
+        let best_placement = self.db.best_placement(server_uuid, user_uuid)?;
         command.create_response(
             &ctx.http, CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content(format!("Balance: {} {} | In bet: {} {}", 
-                        account.balance, config.currency, account.in_bet, config.currency
+                    .content(format!("Balance: {} {} | In bet: {} {}{}{}", 
+                        account.balance, cfg.currency, account.in_bet, cfg.currency,
+                        if bankruptcies > 0 { format!(" | Bankruptcies: {} 💀", bankruptcies) } else { String::new() },
+                        best_placement.map(|(rank, season)| format!(" | Best placement: #{} (season {})", rank, season)).unwrap_or_default()
                     ))
                     .ephemeral(true)
             )
@@ -62,53 +87,418 @@ impl BettingBot {
         Ok((desc, outcomes))
     }
 
+    /// Creates the bet right away if the description and outcomes are given and can be parsed, 
+    /// opens the bet creation form otherwise
     pub async fn bet_command(
         &self,
         ctx: Context,
         command: CommandInteraction,
     ) -> Result<()> {
-        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?;
+        let options = &command.data.options;
+        let desc = options.iter().find(|opt| opt.name == "desc").and_then(|opt| opt.value.as_str());
+        let outcomes_raw = options.iter().find(|opt| opt.name == "options").and_then(|opt| opt.value.as_str());
+        let outcomes = outcomes_raw.and_then(|raw| split(raw).ok());
+        let (Some(desc), Some(outcomes)) = (desc, outcomes.clone()) else {
+            let (closes_in, limits) = Self::bet_settings(&command);
+            let mut desc_input = CreateInputText::new(InputTextStyle::Paragraph, "Description", DESC_INPUT).required(true);
+            if let Some(desc) = desc {
+                desc_input = desc_input.value(desc);
+            }
+            let mut inputs = vec![CreateActionRow::InputText(desc_input)];
+            for (i, id) in OUTCOME_INPUTS.iter().enumerate() {
+                inputs.push(CreateActionRow::InputText(
+                    CreateInputText::new(InputTextStyle::Short, format!("Outcome {}", i+1), *id).required(false)
+                ));
+            }
+            let mut more_input = CreateInputText::new(InputTextStyle::Paragraph, "More outcomes, one per line", MORE_OUTCOMES_INPUT)
+                .required(false);
+            // options that couldn't be parsed are left as is for the user to fix
+            match (outcomes, outcomes_raw) {
+                (Some(outcomes), _) => more_input = more_input.value(outcomes.join("\n")),
+                (None, Some(raw)) => more_input = more_input.value(raw),
+                _ => {}
+            }
+            inputs.push(CreateActionRow::InputText(more_input));
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Modal(
+                    CreateModal::new(
+                        BetAction::BetForm(closes_in, limits.min_stake, limits.max_stake, limits.max_pool_share), "Create a bet"
+                    ).components(inputs)
+                )
+            ).await?;
+            return Ok(());
+        };
+        if let Some(error) = Self::bet_form_error(desc, &outcomes) {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error.clone()).ephemeral(true))
+            ).await?;
+            bail!("invalid bet: {}", error);
+        }
+        self.create_bet(&ctx, &command, desc.to_string(), outcomes).await?;
+        Ok(())
+    }
+
+    /// Submission of the bet creation form
+    pub async fn bet_form_action(
+        &self, ctx: Context, command: &ModalInteraction, closes_in: Option<i64>, limits: StakeLimits
+    ) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let input = |id: &str| command.data.components.iter()
+            .filter_map(|row| row.components.get(0))
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) if input.custom_id == id => input.value.clone(),
+                _ => None
+            })
+            .unwrap_or_default();
+        let desc = input(DESC_INPUT);
+        let outcomes: Vec<String> = OUTCOME_INPUTS.iter().map(|id| input(id))
+            .chain(input(MORE_OUTCOMES_INPUT).lines().map(str::to_string).collect::<Vec<_>>())
+            .map(|outcome| outcome.trim().to_string())
+            .filter(|outcome| !outcome.is_empty())
+            .collect();
+        if let Some(error) = Self::bet_form_error(&desc, &outcomes) {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error.clone()).ephemeral(true))
+            ).await?;
+            bail!("invalid bet: {}", error);
+        }
+        let bet_uuid = self.publish_bet(
+            &ctx, &Interaction::Modal(command.clone()), desc, outcomes.clone(), closes_in, limits
+        ).await?;
+        self.post_outcomes(&ctx, command.channel_id, guild, bet_uuid, &outcomes).await
+    }
+
+    /// Why a bet can't be created with this description and these outcomes, if it can't
+    fn bet_form_error(desc: &str, outcomes: &[String]) -> Option<String> {
+        let duplicate = outcomes.iter().enumerate().find(|(i, outcome)| 
+            outcomes[..*i].iter().any(|other| other.trim().to_lowercase() == outcome.trim().to_lowercase())
+        );
+        if desc.trim().is_empty() {
+            Some("The bet needs a description".to_string())
+        } else if desc.chars().count() > MAX_DESC_LEN {
+            Some(format!("The description can't be longer than {} characters", MAX_DESC_LEN))
+        } else if outcomes.len() < 2 {
+            Some("You must define 2 outcomes or more to create a bet.".to_string())
+        } else if outcomes.len() > MAX_OUTCOMES {
+            Some(format!("A bet can't have more than {} outcomes", MAX_OUTCOMES))
+        } else if outcomes.iter().any(|outcome| outcome.trim().is_empty()) {
+            Some("Outcomes can't be empty".to_string())
+        } else if let Some(outcome) = outcomes.iter().find(|outcome| outcome.chars().count() > MAX_OUTCOME_LEN) {
+            Some(format!("'{}' is too long, outcomes can't be longer than {} characters", shorten(outcome, 30), MAX_OUTCOME_LEN))
+        } else {
+            duplicate.map(|(_, outcome)| format!("'{}' is listed twice", outcome))
+        }
+    }
+
+    /// Over/under bet on a number, resolved by entering the actual number
+    pub async fn number_bet_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let options = &command.data.options;
+        let desc = options.iter().find(|opt| opt.name == "desc")
+            .and_then(|opt| opt.value.as_str())
+            .ok_or(anyhow!("missing desc"))?
+            .to_string();
+        let line = options.iter().find(|opt| opt.name == "line")
+            .and_then(|opt| opt.value.as_f64())
+            .ok_or(anyhow!("missing line"))?;
+        let exact = options.iter().find(|opt| opt.name == "exact")
+            .and_then(|opt| opt.value.as_bool())
+            .unwrap_or(false);
+        let mut outcomes = vec![format!("Over {}", line), format!("Under {}", line)];
+        if exact {
+            outcomes.push(format!("Exactly {}", line));
+        }
+        let bet_uuid = self.create_bet(&ctx, &command, desc, outcomes).await?;
+        self.db.add_number_bet(bet_uuid, line, exact)?;
+        Ok(())
+    }
+
+    /// Fixed odds bet, the author is the bookmaker and puts a bankroll aside to pay the winners
+    pub async fn odds_bet_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let author = command.user.id.get();
         let (desc, outcomes) = Self::bet_parse(&command)?;
-        if outcomes.len() < 2 {
-            command.create_response(
-                &ctx.http,
+        let options = &command.data.options;
+        let odds: Vec<f64> = options.iter().find(|opt| opt.name == "odds")
+            .and_then(|opt| opt.value.as_str())
+            .and_then(|odds| odds.split_whitespace().map(Self::parse_number).collect())
+            .unwrap_or_default();
+        let bankroll = options.iter().find(|opt| opt.name == "bankroll")
+            .and_then(|opt| opt.value.as_i64())
+            .ok_or(anyhow!("missing bankroll"))? as u64;
+        let cfg = self.config(guild)?;
+        self.bets.create_account(guild, author, cfg.starting_coins as u64)?;
+        let balance = self.bets.balance(guild, author)?;
+        let error = Self::bet_form_error(&desc, &outcomes).or_else(|| 
+            if odds.len() != outcomes.len() || odds.iter().any(|odd| *odd <= 1.) {
+                Some("Give one odd greater than 1 for each outcome, like `1.5 2.8 4`".to_string())
+            } else if bankroll > balance {
+                Some(format!("You only have {} {} to put in the bankroll", balance, cfg.currency))
+            } else {
+                None
+            }
+        );
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("invalid fixed odds bet");
+        }
+        // the bankroll is put aside first so it can't be spent while the bet is being posted
+        self.apply_diffs(guild, &[(author, -(bankroll as i64))])?;
+        // until the bookmaker is recorded nothing would give the bankroll back
+        let created = async {
+            let bet_uuid = self.post_bet(&ctx, &command, desc, outcomes.clone()).await?;
+            self.db.add_bookmaker(bet_uuid, &Bookmaker { author, bankroll, odds })?;
+            Ok(bet_uuid)
+        }.await;
+        let bet_uuid = match created {
+            Result::Ok(bet_uuid) => bet_uuid,
+            Err(why) => {
+                self.adjust_balance(guild, author, bankroll as i64)?;
+                bail!(why);
+            }
+        };
+        self.post_outcomes(&ctx, command.channel_id, guild, bet_uuid, &outcomes).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(guild)).await
+    }
+
+    /// Closest guess bet, everyone bets on a number and the closest guesses win
+    pub async fn guess_bet_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let options = &command.data.options;
+        let desc = options.iter().find(|opt| opt.name == "desc")
+            .and_then(|opt| opt.value.as_str())
+            .ok_or(anyhow!("missing desc"))?
+            .to_string();
+        let payout = match options.iter().find(|opt| opt.name == "payout").and_then(|opt| opt.value.as_str()) {
+            Some(payout) => GuessPayout::try_from(payout)?,
+            None => self.config(guild)?.guess_payout
+        };
+        let bet_uuid = self.post_bet(&ctx, &command, desc, vec!["Guesses".to_string()]).await?;
+        self.db.add_guess_bet(bet_uuid, payout)?;
+        let bet_outcome = BetOutcome { bet_id: bet_uuid, outcome_id: 0 };
+        let guesses_msg = self.outcomes_channel(command.channel_id, bet_uuid)?.send_message(&ctx.http, 
+            CreateMessage::new()
+                .content(guesses_display(vec![], &self.config(guild)?.currency))
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(BetAction::BetClick(bet_outcome.clone()))
+                        .label("🎯 Guess")
+                        .style(ButtonStyle::Primary),
+                    CreateButton::new(BetAction::Withdraw(bet_uuid))
+                        .label("↩️ Withdraw")
+                        .style(ButtonStyle::Secondary),
+                ])])
+        ).await?;
+        self.msg_map.insert(bet_outcome, guesses_msg.id.get())?;
+        Ok(())
+    }
+
+    /// Posts the bet and its outcomes, returns the id of the bet
+    async fn create_bet(&self, ctx: &Context, command: &CommandInteraction, desc: String, outcomes: Vec<String>) -> Result<u64> {
+        let bet_uuid = self.post_bet(ctx, command, desc, outcomes.clone()).await?;
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        self.post_outcomes(ctx, command.channel_id, guild, bet_uuid, &outcomes).await?;
+        Ok(bet_uuid)
+    }
+
+    async fn post_outcomes(&self, ctx: &Context, channel_id: ChannelId, guild: u64, bet_uuid: u64, outcomes: &Vec<String>) -> Result<()> {
+        if self.db.is_compact_bet(bet_uuid)? {
+            // the outcomes go in the message of the bet
+            return self.update_outcomes(&ctx.http, channel_id, &bet_stub(bet_uuid, guild, outcomes)).await;
+        }
+        let currency = self.config(guild)?.currency;
+        let outcome_displays = self.render_outcomes(&bet_stub(bet_uuid, guild, outcomes))?;
+        let channel_id = self.outcomes_channel(channel_id, bet_uuid)?;
+        for (i, outcome) in outcome_displays.iter().enumerate() {
+            let outcome_msg = channel_id.send_message(&ctx.http, 
+                CreateMessage::new().content(outcome)
+                .components(Self::outcome_components(BetOutcome { bet_id: bet_uuid, outcome_id: i }, &currency))
+            ).await?;
+            self.msg_map.insert(BetOutcome {bet_id: bet_uuid, outcome_id: i}, outcome_msg.id.get())?;
+        }
+        Ok(())
+    }
+
+    /// The closing delay (in minutes) and stake limits given to a bet creation command
+    fn bet_settings(command: &CommandInteraction) -> (Option<i64>, StakeLimits) {
+        let option = |name: &str| command.data.options.iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| opt.value.as_i64());
+        (option("closes_in"), StakeLimits { 
+            min_stake: option("min_stake").map(|stake| stake as u64), 
+            max_stake: option("max_stake").map(|stake| stake as u64), 
+            max_pool_share: option("max_pool_share").map(|share| share as u32)
+        })
+    }
+
+    /// Posts the message of the bet and registers it, returns the id of the bet
+    async fn post_bet(&self, ctx: &Context, command: &CommandInteraction, desc: String, outcomes: Vec<String>) -> Result<u64> {
+        let (closes_in, limits) = Self::bet_settings(command);
+        self.publish_bet(ctx, &Interaction::Command(command.clone()), desc, outcomes, closes_in, limits).await
+    }
+
+    /// Content of the message of the bet
+    fn bet_header(desc: &str, deadline: Option<i64>, limits: &StakeLimits, currency: &str) -> String {
+        let limits_line = [
+            limits.min_stake.map(|stake| format!("min {} {}", stake, currency)),
+            limits.max_stake.map(|stake| format!("max {} {}", stake, currency)),
+            limits.max_pool_share.map(|share| format!("max {}% of the pool", share)),
+        ].into_iter().flatten().collect::<Vec<_>>().join(", ");
+        "## ".to_string()+desc.trim().trim_start_matches("#").trim_start() 
+            + &deadline.map(|deadline| format!("\n-# 🔒 Locks <t:{}:R>", deadline)).unwrap_or_default()
+            + &if limits_line.is_empty() { String::new() } else { format!("\n-# Stakes: {}", limits_line) }
+    }
+
+    /// Answers the bet creation command or form with the message of the bet and registers it, returns the id of the bet
+    async fn publish_bet(
+        &self, ctx: &Context, interaction: &Interaction, desc: String, outcomes: Vec<String>, 
+        closes_in: Option<i64>, limits: StakeLimits
+    ) -> Result<u64> {
+        let (server_uuid, channel_id, author_uuid) = match interaction {
+            Interaction::Command(command) => (command.guild_id, command.channel_id, command.user.id.get()),
+            Interaction::Modal(command) => (command.guild_id, command.channel_id, command.user.id.get()),
+            _ => bail!("unexpected interaction")
+        };
+        let server_uuid = server_uuid.ok_or(anyhow!("command used outside a server"))?;
+        let deadline = closes_in.map(|minutes| Utc::now().timestamp() + minutes*60);
+        let cfg = self.config(server_uuid.get())?;
+        // the limits the bet doesn't set fall back to the ones of the server
+        let min_stake = limits.min_stake.unwrap_or(cfg.min_stake as u64);
+        let max_stake = limits.max_stake.unwrap_or(cfg.max_stake as u64);
+        if max_stake > 0 && min_stake > max_stake {
+            Self::respond(&ctx.http, interaction, 
                 CreateInteractionResponse::Message(
                     CreateInteractionResponseMessage::new()
-                    .content("You must define 2 outcomes or more to create a bet.")
-                ),
-            )
-            .await?;
-            bail!("Less than 2 ouctomes");
+                        .content(format!("The minimum stake can't be more than the maximum stake of {} {}", max_stake, cfg.currency))
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("min stake above max stake");
         }
-        command.create_response(
+        // closest guess bets have a single outcome that keeps a message of its own
+        let compact = cfg.compact_bets && outcomes.len() >= 2 && Self::fits_in_message(&desc, &outcomes, deadline, &limits, &cfg.currency);
+        Self::respond(
             &ctx.http, 
+            interaction,
             CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                .content("## ".to_string()+desc.trim().trim_start_matches("#").trim_start())
-                .components(vec![CreateActionRow::Buttons(vec![
-                    CreateButton::new(BetAction::Lock).label("🔒 Lock").style(ButtonStyle::Secondary),
-                    CreateButton::new(BetAction::Abort).label("🚫 Abort").style(ButtonStyle::Secondary),
-                ])])
+                .content(Self::bet_header(&desc, deadline, &limits, &cfg.currency))
+                .components(vec![CreateActionRow::Buttons(Self::header_buttons())])
         )).await?;
-        let bet_msg = command.get_response(&ctx.http).await?;
+        let bet_msg = match interaction {
+            Interaction::Command(command) => command.get_response(&ctx.http).await?,
+            Interaction::Modal(command) => command.get_response(&ctx.http).await?,
+            _ => bail!("unexpected interaction")
+        };
         let bet_uuid = bet_msg.id.get();
         let server_uuid = server_uuid.get();
-        let author_uuid = command.user.id.get();
-        self.bets.create_account(server_uuid, author_uuid, config.starting_coins as u64)?;
-        self.bets.create_bet(bet_uuid, server_uuid, author_uuid, desc, &outcomes)?;
-        let outcome_displays = outcomes_display(&bet_stub(&outcomes));
-        for (i, outcome) in outcome_displays.iter().enumerate() {
-            let outcome_msg = command.channel_id.send_message(&ctx.http, 
-                CreateMessage::new().content(outcome)
-                .components(vec![CreateActionRow::Buttons(vec![
-                    CreateButton::new(BetAction::BetClick(BetOutcome { bet_id: bet_uuid, outcome_id: i }))
-                        .label(format!("{} Bet", config.currency))
-                        .style(ButtonStyle::Primary)
-                ])])
-            ).await?;
-            self.msg_map.insert(BetOutcome {bet_id: bet_uuid, outcome_id: i}, outcome_msg.id.get())?;
+        if compact {
+            self.db.set_compact_bet(bet_uuid)?;
+        } else if cfg.bet_threads {
+            // the bet message stays in the channel as a summary, with a link to the thread
+            match channel_id.create_thread_from_message(&ctx.http, bet_msg.id, CreateThread::new(shorten(&desc, 100))).await {
+                Result::Ok(thread) => self.db.set_bet_thread(bet_uuid, channel_id.get(), thread.id.get())?,
+                Err(why) => warn!(target: "betting-bot", "Couldn't create a thread for bet {}: {}", bet_uuid, why),
+            }
         }
-        Ok(())
+        self.bets.create_account(server_uuid, author_uuid, cfg.starting_coins as u64)?;
+        self.bets.create_bet(bet_uuid, server_uuid, author_uuid, desc.clone(), &outcomes)?;
+        self.db.add_bet_record(bet_uuid, server_uuid, author_uuid, &desc, Utc::now().timestamp())?;
+        self.db.set_bet_outcomes(bet_uuid, &outcomes)?;
+        if let Some(deadline) = deadline {
+            self.db.set_deadline(bet_uuid, channel_id.get(), deadline)?;
+        }
+        if limits.min_stake.is_some() || limits.max_stake.is_some() || limits.max_pool_share.is_some() {
+            self.db.set_stake_limits(bet_uuid, &limits)?;
+        }
+        Ok(bet_uuid)
+    }
+
+    fn header_buttons() -> Vec<CreateButton> {
+        vec![
+            CreateButton::new(BetAction::Lock).label("🔒 Lock").style(ButtonStyle::Secondary),
+            CreateButton::new(BetAction::Abort).label("🚫 Abort").style(ButtonStyle::Secondary),
+            CreateButton::new(BetAction::Edit).label("✏️ Edit").style(ButtonStyle::Secondary),
+        ]
+    }
+
+    /// Whether the bet and all of its outcomes fit in a single message, 
+    /// with some room left for the amounts and odds to grow
+    fn fits_in_message(desc: &str, outcomes: &Vec<String>, deadline: Option<i64>, limits: &StakeLimits, currency: &str) -> bool {
+        let content = Self::bet_header(desc, deadline, limits, currency) + "\n" 
+            + &outcomes_display(&bet_stub(0, 0, outcomes), None, currency).join("\n");
+        content.chars().count() <= COMPACT_MAX_LEN
+    }
+
+    /// Content of the message of a compact bet, its header followed by all of its outcomes
+    fn compact_content(&self, bet: &Bet) -> Result<String> {
+        let currency = self.config(bet.server)?.currency;
+        let desc = self.bets.bet_info(bet.bet)?.desc;
+        Ok(Self::bet_header(&desc, self.db.deadline(bet.bet)?, &self.db.stake_limits(bet.bet)?, &currency) 
+            + "\n" + &self.render_outcomes(bet)?.join("\n"))
+    }
+
+    /// Menus to bet on an outcome of a compact bet or move one's bet to it, above the buttons of the bet
+    fn compact_components(bet_id: u64, outcomes: &[String]) -> Vec<CreateActionRow> {
+        let menu = |action: BetAction, placeholder: &str| CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(action, CreateSelectMenuKind::String { 
+                options: outcomes.iter().enumerate()
+                    .map(|(i, outcome)| CreateSelectMenuOption::new(shorten(outcome, 100), i.to_string()))
+                    .collect()
+            }).placeholder(placeholder)
+        );
+        vec![
+            menu(BetAction::PickOutcome(bet_id), "🎲 Bet on…"),
+            menu(BetAction::SwitchPick(bet_id), "🔀 Switch your bet to…"),
+            CreateActionRow::Buttons(Self::header_buttons().into_iter().chain([
+                CreateButton::new(BetAction::Withdraw(bet_id)).label("↩️ Withdraw").style(ButtonStyle::Secondary)
+            ]).collect()),
+        ]
+    }
+
+    /// Outcome picked in one of the menus of a compact bet
+    fn picked_outcome(command: &ComponentInteraction, bet_id: u64) -> Result<BetOutcome> {
+        let ComponentInteractionDataKind::StringSelect { values } = &command.data.kind else {
+            bail!("expected a string select");
+        };
+        let outcome_id = values.first().ok_or(anyhow!("no outcome picked"))?.parse()?;
+        Ok(BetOutcome { bet_id, outcome_id })
+    }
+
+    pub async fn pick_outcome_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.bet_click_action(ctx, command, Self::picked_outcome(command, bet_id)?).await
+    }
+
+    pub async fn switch_pick_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.switch_action(ctx, command, Self::picked_outcome(command, bet_id)?).await
+    }
+
+    pub async fn resolve_pick_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.resolve_action(ctx, command, Self::picked_outcome(command, bet_id)?).await
+    }
+
+    fn outcome_components(bet_outcome: BetOutcome, currency: &str) -> Vec<CreateActionRow> {
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(BetAction::QuickBet(bet_outcome.clone(), 10))
+                .label("10%")
+                .style(ButtonStyle::Secondary),
+            CreateButton::new(BetAction::QuickBet(bet_outcome.clone(), 50))
+                .label("50%")
+                .style(ButtonStyle::Secondary),
+            CreateButton::new(BetAction::QuickBet(bet_outcome.clone(), 100))
+                .label("All in")
+                .style(ButtonStyle::Secondary),
+            CreateButton::new(BetAction::BetClick(bet_outcome.clone()))
+                .label(format!("{} Bet", currency))
+                .style(ButtonStyle::Primary)
+        ]), CreateActionRow::Buttons(vec![
+            CreateButton::new(BetAction::Withdraw(bet_outcome.bet_id))
+                .label("↩️ Withdraw")
+                .style(ButtonStyle::Secondary),
+            CreateButton::new(BetAction::Switch(bet_outcome))
+                .label("🔀 Switch here")
+                .style(ButtonStyle::Secondary),
+        ])]
     }
 
     pub async fn leaderboard_command(
@@ -117,213 +507,2556 @@ impl BettingBot {
         command: CommandInteraction,
     ) -> Result<()> {
         let guild_id = command.guild_id.ok_or(anyhow!("command used outside a server"))?;
-        let mut accounts = self.bets.accounts(guild_id.get())?;
-        // sort by balance+inbet first and balance to tie break
-        accounts.sort_by_key(|acc| (acc.balance+acc.in_bet, acc.balance));
-        accounts.reverse();
-        let msg = format!("{}  ({} in bet)   user\n", config.currency, config.currency) 
-        + &accounts.into_iter().take(10).map(|acc| 
-            format!("{}  ({})   <@{}>", acc.balance, acc.in_bet, acc.user)
-        ).join("\n") + "\n...";
+        let permanent = command.data.options.iter()
+            .find(|opt| opt.name == "permanent")
+            .and_then(|opt| opt.value.as_bool())
+            .unwrap_or(false);
+        let season = command.data.options.iter()
+            .find(|opt| opt.name == "season")
+            .and_then(|opt| opt.value.as_i64());
+        if let Some(season) = season {
+            let currency = self.config(guild_id.get())?.currency;
+            let (msg, found) = match self.db.season(guild_id.get(), season as u32)? {
+                Some((ended, standings)) => (season_display(season as u32, ended, &standings, &currency), true),
+                None => (format!("Season {} isn't over yet or never happened", season), false)
+            };
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(msg).ephemeral(!found))
+            ).await?;
+            return Ok(());
+        }
+        let msg = self.leaderboard(guild_id.get())?;
         command.create_response(&ctx.http, 
             CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(msg))
         ).await?;
+        if permanent {
+            let leaderboard_msg = command.get_response(&ctx.http).await?;
+            self.db.set_leaderboard(guild_id.get(), command.channel_id.get(), leaderboard_msg.id.get())?;
+        }
         Ok(())
     }
 
-    async fn is_admin(&self, command: &ComponentInteraction) -> Result<bool> {
-        if let Some(member) = &command.user.member {
-            let permissions = member.permissions.ok_or(anyhow!("couldn't get permissions"))?;
-            return Ok(permissions.administrator());
-        }
-        bail!("couldn't get member");
+    fn leaderboard(&self, guild: u64) -> Result<String> {
+        let cfg = self.config(guild)?;
+        Ok(leaderboard_display(self.bets.accounts(guild)?, &self.db.bankruptcies(guild)?, cfg.hide_bankrupt, &cfg.currency))
     }
 
-    pub async fn check_rights(&self, ctx: &Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
-        let user_uuid = command.user.id.get();
-        let info = self.bets.bet_info(bet_id)?;
-        if info.author != user_uuid && !self.is_admin(command).await? {
-            command.create_response(
-                &ctx.http, 
-                CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new()
-                        .content("Only the bet author or admins can perform this action")
-                        .ephemeral(true)
-                    )
-            ).await?;
-            bail!("user is not bet author and not admin");
-        }
+    pub async fn history_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild_id = command.guild_id.ok_or(anyhow!("command used outside a server"))?;
+        let user = command.data.options.iter()
+            .find(|opt| opt.name == "user")
+            .and_then(|opt| opt.value.as_user_id())
+            .unwrap_or(command.user.id);
+        let (content, components) = self.history_page(guild_id.get(), user.get(), 0)?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(content).components(components).ephemeral(true)
+            )
+        ).await?;
         Ok(())
     }
-    
-    pub async fn lock_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
-        self.check_rights(&ctx, command, bet_id).await?;
-        self.bets.lock_bet(bet_id)?;
-        command.create_response(
-            &ctx.http,
-            CreateInteractionResponse::UpdateMessage(
-                CreateInteractionResponseMessage::new().components(vec![CreateActionRow::Buttons(vec![
-                    CreateButton::new(BetAction::Abort).label("🚫 Abort").style(ButtonStyle::Secondary)
-                ])])
-            )
+
+    pub async fn stats_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let server_wide = command.data.options.iter()
+            .find(|opt| opt.name == "server")
+            .and_then(|opt| opt.value.as_bool())
+            .unwrap_or(false);
+        let currency = self.config(guild)?.currency;
+        let content = if server_wide {
+            let (bets, bettors, volume) = self.db.guild_volume(guild)?;
+            guild_stats_display(bets, bettors, volume, &self.db.popular_bets(guild, 3)?, &currency)
+        } else {
+            let user = command.data.options.iter()
+                .find(|opt| opt.name == "user")
+                .and_then(|opt| opt.value.as_user_id())
+                .unwrap_or(command.user.id)
+                .get();
+            let stats = UserStats::new(
+                self.db.positions_len(guild, user)? as u64, 
+                &self.db.settled_positions(guild, user)?, 
+                self.db.favourite_author(guild, user)?
+            );
+            user_stats_display(user, &stats, &currency)
+        };
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(content))
         ).await?;
-        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
-            let outcome = BetOutcome { bet_id, outcome_id: outcome_id as usize };
-            let msg_id = self.msg_map.get(outcome.clone())?;
-            let mut message = ctx.http.get_message(command.channel_id, msg_id.into()).await?;
-            message.edit(&ctx.http, 
-                EditMessage::new().components(vec![CreateActionRow::Buttons(vec![
-                    CreateButton::new(BetAction::Resolve(outcome))
-                        .label("🏆 Resolve")
-                        .style(ButtonStyle::Secondary)
-                ])])
-            ).await?;
-        }
         Ok(())
     }
 
-    pub async fn abort_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
-        self.check_rights(&ctx, command, bet_id).await?;
-        self.bets.abort_bet(bet_id)?;
-        command.create_response(
-            &ctx.http, 
+    pub async fn history_action(&self, ctx: Context, command: &ComponentInteraction, user: u64, page: usize) -> Result<()> {
+        let guild_id = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        let (content, components) = self.history_page(guild_id.get(), user, page)?;
+        command.create_response(&ctx.http, 
             CreateInteractionResponse::UpdateMessage(
-                CreateInteractionResponseMessage::new().content("*Bet aborted, participants have been refunded.*")
+                CreateInteractionResponseMessage::new().content(content).components(components)
             )
         ).await?;
-        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
-            let outcome = BetOutcome { bet_id, outcome_id: outcome_id as usize };
-            let msg_id = self.msg_map.get(outcome.clone())?;
-            ctx.http.delete_message(command.channel_id, msg_id.into(), None).await?;
-        }
         Ok(())
     }
 
-    pub async fn bet_click_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
-        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
-        let user_uuid = command.user.id.get();
-        self.bets.create_account(server_uuid, user_uuid, config.starting_coins as u64)?;
-        let balance = self.bets.balance(server_uuid, user_uuid)?;
-        let bet_info = self.bets.bet_info(bet_outcome.bet_id)?;
-        let outcome_text = shorten(&command.message.content.trim_start_matches("## >"), 20);
-        let previous_bet = match self.bets.position(user_uuid, bet_outcome.bet_id) {
-            Result::Ok(position) => {
-                if position.outcome != bet_outcome.outcome_id {
-                    command.create_response(
-                        &ctx.http, 
-                CreateInteractionResponse::Message(
-                            CreateInteractionResponseMessage::new().content(
-                                format!("You put a bet on option #{} and can only bet on one option", position.outcome+1)
-                            )
-                            .ephemeral(true)
-                        )
-                    ).await?;
-                    bail!("user tried to bet on multiple option");        
-                }
-                position.amount
-            },
-            Err(betting::BetError::NotFound) => 0,
-            Err(err) => bail!(err)
-        };
-        command.create_response(
-            &ctx.http, 
-            CreateInteractionResponse::Modal(
-                CreateModal::new(
-                    BetAction::BetOrder, 
-                    format!("[{} {}] {}", balance, config.currency, shorten(&bet_info.desc, 20))
-                ).components(vec![
-                    CreateActionRow::InputText(
-                        CreateInputText::new(
-                            InputTextStyle::Short, 
-                            format!("[{} {}] Bet on: {}", previous_bet, config.currency, outcome_text),
-                            bet_outcome.to_string()
-                        ).placeholder("100").required(true)
-                    )
-                ])
-            )).await?;
-        Ok(())
+    fn history_page(&self, guild: u64, user: u64, page: usize) -> Result<(String, Vec<CreateActionRow>)> {
+        let pages = max(self.db.history_len(guild, user)?.div_ceil(PAGE_SIZE), 1);
+        let page = min(page, pages-1);
+        let entries = self.db.history(guild, user, page*PAGE_SIZE, PAGE_SIZE)?;
+        let components = Self::page_components(page, pages, |page| BetAction::History(user, page));
+        Ok((history_display(user, &entries, page, pages, &self.config(guild)?.currency), components))
     }
 
-    pub async fn bet_order_action(&self, ctx: Context, command: &ModalInteraction) -> Result<()> {
+    /// Previous and Next buttons, if there's more than 1 page
+    fn page_components(page: usize, pages: usize, action: impl Fn(usize) -> BetAction) -> Vec<CreateActionRow> {
+        if pages <= 1 {
+            return vec![];
+        }
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(action(page.saturating_sub(1)))
+                .label("◀ Previous")
+                .style(ButtonStyle::Secondary)
+                .disabled(page == 0),
+            CreateButton::new(action(page+1))
+                .label("Next ▶")
+                .style(ButtonStyle::Secondary)
+                .disabled(page+1 >= pages),
+        ])]
+    }
+
+    pub async fn daily_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
         let user = command.user.id.get();
-        if let ActionRowComponent::InputText(input) = &(&command.data.components[0]).components[0] {
-            let bet_outcome = BetOutcome::try_from(input.custom_id.as_ref())?;
-            let amount: u64 = <Option<String> as Clone>::clone(&input.value).unwrap().parse()?;
-            let (acc_update, bet) = self.bets.bet_on(bet_outcome.bet_id, bet_outcome.outcome_id, user, amount)?;
-            let total: u64 = bet.outcomes[bet_outcome.outcome_id].wagers
-                .iter().filter(|(u, _)| *u == user).map(|(_, a)| a).sum();
-            command.create_response(
-                &ctx.http, 
+        let cfg = self.config(guild)?;
+        self.bets.create_account(guild, user, cfg.starting_coins as u64)?;
+        let now = Utc::now().timestamp();
+        let today = now.div_euclid(DAY);
+        let (last_day, streak) = self.db.daily(guild, user)?
+            .map(|(last_claim, streak)| (last_claim.div_euclid(DAY), streak))
+            .unwrap_or((i64::MIN, 0));
+        // the streak is lost if a day was missed
+        let streak = if last_day == today-1 { streak+1 } else { 1 };
+        // the claim is recorded first so that 2 concurrent claims can't both be paid
+        if last_day == today || !self.db.claim_daily(guild, user, now, streak, today*DAY)? {
+            command.create_response(&ctx.http, 
                 CreateInteractionResponse::Message(
                     CreateInteractionResponseMessage::new()
-                        .content(format!(
-                            "Succesfully bet {} {} (total {} {}) on:\n## > {}\nnew balance: {} {}", 
-                            amount, config.currency, total, config.currency, bet.outcomes[bet_outcome.outcome_id].desc, acc_update.balance, config.currency
-                        ))
+                        .content(format!("You already claimed your daily coins, come back <t:{}:R>", (today+1)*DAY))
                         .ephemeral(true)
                 )
             ).await?;
-            for (i, outcome) in outcomes_display(&bet).iter().enumerate() {
-                let msg_id = self.msg_map.get(BetOutcome { bet_id: bet_outcome.bet_id, outcome_id: i })?;
-                let mut msg = ctx.http.get_message(command.channel_id, msg_id.into()).await?;
-                msg.edit(&ctx.http, EditMessage::new().content(outcome)).await?;
-            }
+            bail!("daily already claimed");
         }
-        Ok(())
+        let amount = cfg.daily_amount(streak);
+        self.adjust_balance(guild, user, amount as i64)?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "You claimed {} {} (🔥 {} day streak)\n-# come back tomorrow to keep your streak going!", 
+                        amount, cfg.currency, streak
+                    ))
+                    .ephemeral(true)
+            )
+        ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(guild)).await
     }
 
-    pub async fn resolve_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
-        let Some(guild_id) = command.guild_id else {
-            bail!("Couldn't find the guild id");
+    pub async fn rebuy_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let user = command.user.id.get();
+        let cfg = self.config(guild)?;
+        self.bets.create_account(guild, user, cfg.starting_coins as u64)?;
+        let account = self.bets.account(guild, user)?;
+        let now = Utc::now().timestamp();
+        let cooldown = cfg.rebuy_cooldown as i64*60*60;
+        let cooldown_end = self.db.bankruptcy(guild, user)?
+            .map(|(_, last_rebuy)| last_rebuy + cooldown)
+            .unwrap_or(i64::MIN);
+        // coins put aside for fixed odds bets, challenges and parlays count as in bet too
+        let in_bet = account.in_bet + self.db.escrowed(guild, user)?;
+        let error = if account.balance > 0 || in_bet > 0 {
+            Some(format!(
+                "You can only rebuy when you're broke, you still have {} {} (+{} {} in bet)", 
+                account.balance, cfg.currency, in_bet, cfg.currency
+            ))
+        } else if cooldown_end > now || !self.db.add_bankruptcy(guild, user, now, now - cooldown)? {
+            // the rebuy is recorded before paying so that 2 concurrent rebuys can't both be paid
+            Some(format!("You rebought recently, you can rebuy again <t:{}:R>", cooldown_end.max(now + cooldown)))
+        } else {
+            None
         };
-        self.check_rights(&ctx, command, bet_outcome.bet_id).await?;
-        let account_diffs = self.bets.resolve(bet_outcome.bet_id, bet_outcome.outcome_id)?;
-        let total = account_diffs.into_iter().fold(0, |sum, acc| sum+acc.diff);
-        // Everyone wins a little activity bonus
-        self.bets.income(guild_id.get(), config.income as u64)?;
-        command.create_response(
-            &ctx.http, 
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("rebuy not allowed");
+        }
+        self.adjust_balance(guild, user, cfg.rebuy as i64)?;
+        command.create_response(&ctx.http, 
             CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content(format!(
-                        "## Bet resolved as\n{}\n{} {} is shared between the winners.\n-# everyone also wins {}`{}` for activity bonus !", 
-                        command.message.content.clone(), total, config.currency, config.income, config.currency
-                    ))
+                    .content(format!("💀 <@{}> went bankrupt and rebought for {} {}", user, cfg.rebuy, cfg.currency))
             )
         ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(guild)).await
+    }
 
-        let mut bet_msg = ctx.http.get_message(command.channel_id, bet_outcome.bet_id.into()).await?;
-        let bet_msg_content = bet_msg.content.clone();
-        bet_msg.edit(&ctx.http, 
-            EditMessage::new()
-                .content(format!("*Resolved {}*\n{}", Local::now().format("%d/%m/%Y"), bet_msg_content))
-                .components(vec![])
+    pub async fn give_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let sender = command.user.id.get();
+        let options = &command.data.options;
+        let recipient = options.iter().find(|opt| opt.name == "user")
+            .and_then(|opt| opt.value.as_user_id())
+            .ok_or(anyhow!("missing user"))?
+            .get();
+        let amount = options.iter().find(|opt| opt.name == "amount")
+            .and_then(|opt| opt.value.as_i64())
+            .ok_or(anyhow!("missing amount"))? as u64;
+        let note = options.iter().find(|opt| opt.name == "note")
+            .and_then(|opt| opt.value.as_str())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let cfg = self.config(guild)?;
+        self.bets.create_account(guild, sender, cfg.starting_coins as u64)?;
+        self.bets.create_account(guild, recipient, cfg.starting_coins as u64)?;
+        // the balance doesn't include what's in bet
+        let balance = self.bets.balance(guild, sender)?;
+        let error = if recipient == sender {
+            Some("You can't give coins to yourself".to_string())
+        } else if amount > balance {
+            Some(format!("You only have {} {} available", balance, cfg.currency))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("invalid transfer");
+        }
+        self.apply_diffs(guild, &[(sender, -(amount as i64)), (recipient, amount as i64)])?;
+        self.db.add_transfer(guild, sender, recipient, amount, &note, Utc::now().timestamp())?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(format!(
+                    "🎁 <@{}> gave {} {} to <@{}>{}", sender, amount, cfg.currency, recipient,
+                    if note.is_empty() { String::new() } else { format!("\n> {}", note) }
+                ))
+            )
         ).await?;
-        for outcome_id in self.bets.outcomes_of_bet(bet_outcome.bet_id)? {
-            let msg_id = self.msg_map.get(BetOutcome { bet_id: bet_outcome.bet_id, outcome_id: outcome_id as usize })?;
-            let mut message = ctx.http.get_message(command.channel_id, msg_id.into()).await?;
-            message.edit(&ctx.http, EditMessage::new().components(vec![])).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(guild)).await
+    }
+
+    /// /grant (sign = 1) and /take (sign = -1)
+    pub async fn adjust_command(&self, ctx: Context, command: CommandInteraction, sign: i64) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        self.check_admin(&ctx, &command).await?;
+        let options = &command.data.options;
+        let user = options.iter().find(|opt| opt.name == "user")
+            .and_then(|opt| opt.value.as_user_id())
+            .ok_or(anyhow!("missing user"))?
+            .get();
+        let amount = options.iter().find(|opt| opt.name == "amount")
+            .and_then(|opt| opt.value.as_i64())
+            .ok_or(anyhow!("missing amount"))?;
+        let reason = options.iter().find(|opt| opt.name == "reason")
+            .and_then(|opt| opt.value.as_str())
+            .ok_or(anyhow!("missing reason"))?
+            .to_string();
+        let cfg = self.config(guild)?;
+        self.bets.create_account(guild, user, cfg.starting_coins as u64)?;
+        let balance = self.bets.balance(guild, user)?;
+        if sign < 0 && amount as u64 > balance {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("<@{}> only has {} {} available", user, balance, cfg.currency))
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("tried to take more than the balance");
         }
+        let delta = sign*amount;
+        self.adjust_balance(guild, user, delta)?;
+        self.db.add_audit(guild, &AuditEntry { 
+            admin: command.user.id.get(), user, delta, reason: reason.clone(), at: Utc::now().timestamp() 
+        })?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(format!(
+                    "<@{}> {} {} {} {} <@{}>: {}", 
+                    command.user.id, if sign >= 0 { "granted" } else { "took" }, amount, cfg.currency, 
+                    if sign >= 0 { "to" } else { "from" }, user, reason
+                ))
+            )
+        ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(guild)).await
+    }
+
+    pub async fn audit_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        self.check_admin(&ctx, &command).await?;
+        let (content, components) = self.audit_page(guild, 0)?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(content).components(components).ephemeral(true)
+            )
+        ).await?;
         Ok(())
     }
 
-    pub async fn register_commands(&self, http: &Http, id: GuildId) {
-        println!("Registering slash commands for Guild {}", id);
-        if let Err(why) =
-            id.set_commands(http, vec![
-                CreateCommand::new("account").description("Check how much you have in your account."),
-                CreateCommand::new("bet")
-                    .description("Create a bet.")
-                    .add_option(CreateCommandOption::new(
-                        CommandOptionType::String, 
-                        "desc", 
-                        "The description of the bet"
-                    ).required(true))
-                    .add_option(CreateCommandOption::new(
-                        CommandOptionType::String, 
-                        "options", 
+    pub async fn audit_action(&self, ctx: Context, command: &ComponentInteraction, page: usize) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let (content, components) = self.audit_page(guild, page)?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content(content).components(components)
+            )
+        ).await?;
+        Ok(())
+    }
+
+    fn audit_page(&self, guild: u64, page: usize) -> Result<(String, Vec<CreateActionRow>)> {
+        let pages = max(self.db.audit_len(guild)?.div_ceil(PAGE_SIZE), 1);
+        let page = min(page, pages-1);
+        let entries = self.db.audit(guild, page*PAGE_SIZE, PAGE_SIZE)?;
+        let components = Self::page_components(page, pages, BetAction::Audit);
+        Ok((audit_display(&entries, page, pages, &self.config(guild)?.currency), components))
+    }
+
+    pub async fn season_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        self.check_admin(&ctx, &command).await?;
+        let subcommand = command.data.options.get(0).ok_or(anyhow!("missing subcommand"))?;
+        if subcommand.name != "end" {
+            bail!("unknown subcommand '{}'", subcommand.name);
+        }
+        let cfg = self.config(guild)?;
+        let season = self.db.current_season(guild)?;
+        let mut accounts = self.bets.accounts(guild)?;
+        ranking(&mut accounts);
+        // coins still at stake would be paid out into the new season on top of the reset balances
+        let mut at_stake = false;
+        for acc in &accounts {
+            at_stake |= acc.in_bet > 0 || self.db.escrowed(guild, acc.user)? > 0;
+        }
+        if at_stake {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Resolve or abort the open bets, challenges and parlays before ending the season")
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("coins are still at stake");
+        }
+        let standings: Vec<Standing> = accounts.iter()
+            .map(|acc| Standing { user: acc.user, balance: acc.balance, in_bet: acc.in_bet })
+            .collect();
+        let resets: Vec<(u64, i64)> = accounts.iter()
+            .map(|acc| (acc.user, cfg.starting_coins as i64 - acc.balance as i64))
+            .collect();
+        self.apply_diffs(guild, &resets)?;
+        if let Err(why) = self.db.archive_season(guild, season, &standings, Utc::now().timestamp()) {
+            let restores: Vec<(u64, i64)> = resets.iter().map(|(user, diff)| (*user, -diff)).collect();
+            self.apply_diffs(guild, &restores)?;
+            return Err(why);
+        }
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(
+                format!(
+                    "🏁 Season {} is over! Every balance is back to {} {}, good luck in season {}\n", 
+                    season, cfg.starting_coins, cfg.currency, season+1
+                ) + &season_display(season, Utc::now().timestamp(), &standings, &cfg.currency)
+            ))
+        ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(guild)).await
+    }
+
+    pub async fn pot_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let subcommand = command.data.options.get(0).ok_or(anyhow!("missing subcommand"))?;
+        let cfg = self.config(guild)?;
+        let pot = self.db.pot(guild)?;
+        if subcommand.name == "view" {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(format!(
+                    "🏦 The community pot holds {} {}\n-# {}% of the winnings of every bet goes into it", pot, cfg.currency, cfg.rake
+                )).ephemeral(true))
+            ).await?;
+            return Ok(());
+        }
+        self.check_admin(&ctx, &command).await?;
+        let mut accounts = self.bets.accounts(guild)?;
+        ranking(&mut accounts);
+        let recipients: Vec<(u64, u64)> = match subcommand.name.as_str() {
+            "lottery" if !accounts.is_empty() => {
+                let winner = &accounts[rand::random::<u64>() as usize % accounts.len()];
+                vec![(winner.user, pot)]
+            },
+            "top" => {
+                let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+                    bail!("expected a subcommand");
+                };
+                let n = options.iter().find(|opt| opt.name == "n")
+                    .and_then(|opt| opt.value.as_i64())
+                    .ok_or(anyhow!("missing n"))? as usize;
+                let top: Vec<u64> = accounts.iter().take(n).map(|acc| acc.user).collect();
+                top.iter().cloned().zip(lrm(pot, &vec![1; top.len()])).collect()
+            },
+            "bonus" if !accounts.is_empty() => {
+                let share = pot/accounts.len() as u64;
+                accounts.iter().map(|acc| (acc.user, share)).collect()
+            },
+            "lottery" | "bonus" => vec![],
+            other => bail!("unknown subcommand '{}'", other)
+        };
+        let recipients: Vec<(u64, u64)> = recipients.into_iter().filter(|(_, amount)| *amount > 0).collect();
+        if recipients.is_empty() {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().content("There's nothing to distribute").ephemeral(true)
+                )
+            ).await?;
+            bail!("empty pot distribution");
+        }
+        // the coins leave the pot before they're paid so they can't be distributed twice
+        let distributed: u64 = recipients.iter().map(|(_, amount)| amount).sum();
+        self.db.add_to_pot(guild, -(distributed as i64))?;
+        if let Err(why) = self.apply_diffs(guild, &recipients.iter().map(|(user, amount)| (*user, *amount as i64)).collect::<Vec<_>>()) {
+            self.db.add_to_pot(guild, distributed as i64)?;
+            bail!(why);
+        }
+        let reason = format!("community pot {}", subcommand.name);
+        let now = Utc::now().timestamp();
+        for (user, amount) in &recipients {
+            self.db.add_audit(guild, &AuditEntry { 
+                admin: command.user.id.get(), user: *user, delta: *amount as i64, reason: reason.clone(), at: now 
+            })?;
+        }
+        let content = match subcommand.name.as_str() {
+            "lottery" => format!("🎰 <@{}> won the community pot: {} {}!", recipients[0].0, distributed, cfg.currency),
+            "bonus" => format!("🏦 The community pot was shared with everyone, {} {} each!", recipients[0].1, cfg.currency),
+            _ => format!("🏦 The community pot was shared between the top {}:\n", recipients.len()) 
+                + &recipients.iter().map(|(user, amount)| format!("<@{}> +{} {}", user, amount, cfg.currency)).collect::<Vec<_>>().join("\n")
+        };
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(content))
+        ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(guild)).await
+    }
+
+    pub async fn config_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        self.check_admin(&ctx, &command).await?;
+        let subcommand = command.data.options.get(0).ok_or(anyhow!("missing subcommand"))?;
+        let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+            bail!("expected a subcommand");
+        };
+        let option = |name: &str| options.iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| opt.value.as_str())
+            .map(|value| value.to_string());
+        let content = match subcommand.name.as_str() {
+            "view" => config_display(&self.config(guild)?),
+            "set" => {
+                let key = option("setting").ok_or(anyhow!("missing setting"))?;
+                let value = option("value").ok_or(anyhow!("missing value"))?;
+                // make sure the value is valid before saving it
+                match self.config(guild)?.set(&key, &value) {
+                    Result::Ok(()) => {
+                        self.db.set_setting(guild, &key, value.trim())?;
+                        format!("`{}` is now set to `{}`", key, value.trim())
+                    },
+                    Err(why) => format!("Couldn't set `{}` to `{}`: {}", key, value, why)
+                }
+            },
+            "reset" => {
+                let key = option("setting").ok_or(anyhow!("missing setting"))?;
+                self.db.remove_setting(guild, &key)?;
+                format!("`{}` is back to its default value", key)
+            },
+            other => bail!("unknown subcommand '{}'", other)
+        };
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(content).ephemeral(true))
+        ).await?;
+        Ok(())
+    }
+
+    /// Re-renders every permanent leaderboard of the guild, forgetting the ones that were deleted
+    pub async fn update_leaderboards(&self, http: &Http, guild_id: GuildId) -> Result<()> {
+        let leaderboards = self.db.leaderboards(guild_id.get())?;
+        if leaderboards.is_empty() {
+            return Ok(());
+        }
+        let msg = self.leaderboard(guild_id.get())?;
+        for (channel_id, msg_id) in leaderboards {
+            match http.get_message(channel_id.into(), msg_id.into()).await {
+                Result::Ok(mut message) => {
+                    message.edit(http, EditMessage::new().content(msg.clone())).await?;
+                },
+                // only a deleted message is untracked, other errors may be temporary
+                Err(serenity::Error::Http(HttpError::UnsuccessfulRequest(response))) if response.status_code.as_u16() == 404 => {
+                    warn!(target: "betting-bot", "Leaderboard {} in channel {} is gone, untracking it", msg_id, channel_id);
+                    self.db.remove_leaderboard(guild_id.get(), channel_id)?;
+                },
+                Err(why) => warn!(target: "betting-bot", "Couldn't update leaderboard {} in channel {}: {}", msg_id, channel_id, why),
+            }
+        }
+        Ok(())
+    }
+
+    fn is_admin(member: Option<&Member>) -> Result<bool> {
+        if let Some(member) = member {
+            let permissions = member.permissions.ok_or(anyhow!("couldn't get permissions"))?;
+            return Ok(permissions.administrator());
+        }
+        bail!("couldn't get member");
+    }
+
+    fn has_rights(&self, user: &User, member: Option<&Member>, bet_id: u64) -> Result<bool> {
+        let info = self.bets.bet_info(bet_id)?;
+        Ok(info.author == user.id.get() || Self::is_admin(member)?)
+    }
+
+    /// Answers with an error and bails if the user of the command is not an admin
+    pub async fn check_admin(&self, ctx: &Context, command: &CommandInteraction) -> Result<()> {
+        if !Self::is_admin(command.member.as_deref())? {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only admins can use this command")
+                        .ephemeral(true)
+                    )
+            ).await?;
+            bail!("user is not admin");
+        }
+        Ok(())
+    }
+
+    pub async fn check_rights(&self, ctx: &Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        if !self.has_rights(&command.user, command.member.as_ref(), bet_id)? {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only the bet author or admins can perform this action")
+                        .ephemeral(true)
+                    )
+            ).await?;
+            bail!("user is not bet author and not admin");
+        }
+        Ok(())
+    }
+    
+    pub async fn lock_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.check_rights(&ctx, command, bet_id).await?;
+        let _guard = self.guard_bet(bet_id).await;
+        self.bets.lock_bet(bet_id)?;
+        self.db.set_bet_status(bet_id, BetStatus::Locked)?;
+        self.lock_parlay_odds(bet_id)?;
+        self.db.remove_deadline(bet_id)?;
+        // the outcomes of a compact bet are in this message, nothing else needs to be edited
+        let compact = self.db.is_compact_bet(bet_id)?;
+        let outcomes = if compact { self.db.bet_outcomes(bet_id)? } else { vec![] };
+        command.create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().components(self.locked_components(bet_id, &outcomes)?)
+            )
+        ).await?;
+        if compact {
+            return Ok(());
+        }
+        let outcomes = self.lock_outcomes(&ctx.http, command.channel_id, bet_id).await?;
+        let mut bet_msg = self.bet_message(&ctx.http, command.channel_id, bet_id).await?;
+        bet_msg.edit(&ctx.http, EditMessage::new().components(self.locked_components(bet_id, &outcomes)?)).await?;
+        Ok(())
+    }
+
+    /// Locks a bet whose deadline has passed, same as if the author had clicked Lock
+    pub async fn auto_lock(&self, http: &Http, channel_id: ChannelId, bet_id: u64) -> Result<()> {
+        let _guard = self.guard_bet(bet_id).await;
+        self.db.remove_deadline(bet_id)?;
+        self.bets.lock_bet(bet_id)?;
+        self.db.set_bet_status(bet_id, BetStatus::Locked)?;
+        self.lock_parlay_odds(bet_id)?;
+        let outcomes = self.lock_outcomes(http, channel_id, bet_id).await?;
+        let mut bet_msg = self.bet_message(http, channel_id, bet_id).await?;
+        bet_msg.edit(http, EditMessage::new().components(self.locked_components(bet_id, &outcomes)?)).await?;
+        Ok(())
+    }
+
+    /// Over/under and closest guess bets are resolved by entering a number instead of picking an outcome
+    fn resolved_by_number(&self, bet_id: u64) -> Result<bool> {
+        Ok(self.db.number_bet(bet_id)?.is_some() || self.db.guess_bet(bet_id)?.is_some())
+    }
+
+    /// Abort button, and a menu to pick several winners if the outcomes are known 
+    /// or a button to enter the result of bets resolved by a number
+    fn locked_components(&self, bet_id: u64, outcomes: &[String]) -> Result<Vec<CreateActionRow>> {
+        let mut abort_row = vec![
+            CreateButton::new(BetAction::Abort).label("🚫 Abort").style(ButtonStyle::Secondary)
+        ];
+        if self.resolved_by_number(bet_id)? {
+            abort_row.push(
+                CreateButton::new(BetAction::NumberResult(bet_id)).label("🔢 Enter result").style(ButtonStyle::Primary)
+            );
+            return Ok(vec![CreateActionRow::Buttons(abort_row)]);
+        }
+        let mut components = vec![CreateActionRow::Buttons(abort_row)];
+        // a compact bet has no outcome messages to put the Resolve buttons on
+        if !outcomes.is_empty() && self.db.is_compact_bet(bet_id)? {
+            components.push(CreateActionRow::SelectMenu(
+                CreateSelectMenu::new(BetAction::ResolvePick(bet_id), CreateSelectMenuKind::String { 
+                    options: outcomes.iter().enumerate()
+                        .map(|(i, outcome)| CreateSelectMenuOption::new(shorten(outcome, 100), i.to_string()))
+                        .collect()
+                })
+                .placeholder("🏆 Resolve")
+            ));
+        }
+        // fixed odds only work with a single winner
+        if outcomes.len() >= 2 && self.db.bookmaker(bet_id)?.is_none() {
+            components.push(CreateActionRow::SelectMenu(
+                CreateSelectMenu::new(BetAction::SplitSelect(bet_id), CreateSelectMenuKind::String { 
+                    options: outcomes.iter().enumerate()
+                        .map(|(i, outcome)| CreateSelectMenuOption::new(shorten(outcome, 100), i.to_string()))
+                        .collect()
+                })
+                .placeholder("⚖️ Resolve with several winners")
+                .min_values(2)
+                // a modal can only hold 5 weights
+                .max_values(min(outcomes.len(), 5) as u8)
+            ));
+        }
+        Ok(components)
+    }
+
+    /// Replaces the bet buttons with Resolve buttons and returns the outcomes descriptions, 
+    /// bets resolved by a number don't get any
+    async fn lock_outcomes(&self, http: &Http, channel_id: ChannelId, bet_id: u64) -> Result<Vec<String>> {
+        if self.db.is_compact_bet(bet_id)? {
+            return self.db.bet_outcomes(bet_id);
+        }
+        let is_number_bet = self.resolved_by_number(bet_id)?;
+        let mut outcomes = Vec::new();
+        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
+            let outcome = BetOutcome { bet_id, outcome_id: outcome_id as usize };
+            let mut message = self.outcome_message(http, channel_id, outcome.clone()).await?;
+            message.edit(http, 
+                EditMessage::new().components(if is_number_bet { vec![] } else { 
+                    vec![CreateActionRow::Buttons(vec![
+                        CreateButton::new(BetAction::Resolve(outcome))
+                            .label("🏆 Resolve")
+                            .style(ButtonStyle::Secondary)
+                    ])]
+                })
+            ).await?;
+            outcomes.push(Self::outcome_desc(&message.content));
+        }
+        // the messages are only read for the bets that predate bet_outcomes
+        let recorded = self.db.bet_outcomes(bet_id)?;
+        Ok(if recorded.is_empty() { outcomes } else { recorded })
+    }
+
+    /// Channel of the outcome messages of the bet, its thread if it has one
+    fn outcomes_channel(&self, channel_id: ChannelId, bet_id: u64) -> Result<ChannelId> {
+        Ok(self.db.bet_thread(bet_id)?.map(|(_, thread)| ChannelId::new(thread)).unwrap_or(channel_id))
+    }
+
+    /// Channel of the message of the bet, which stays in the parent channel if the bet has a thread
+    fn bet_channel(&self, channel_id: ChannelId, bet_id: u64) -> Result<ChannelId> {
+        Ok(self.db.bet_thread(bet_id)?.map(|(channel, _)| ChannelId::new(channel)).unwrap_or(channel_id))
+    }
+
+    async fn bet_message(&self, http: &Http, channel_id: ChannelId, bet_id: u64) -> Result<Message> {
+        Ok(http.get_message(self.bet_channel(channel_id, bet_id)?, bet_id.into()).await?)
+    }
+
+    /// Display of the outcome with the positions currently on it, must be taken before the bet is settled
+    async fn outcome_content(&self, http: &Http, channel_id: ChannelId, server: u64, bet_outcome: BetOutcome) -> Result<String> {
+        let bet = self.current_bet(server, bet_outcome.bet_id)?;
+        if bet.outcomes.is_empty() {
+            // the bets that predate bet_outcomes only have their outcomes in their messages
+            return Ok(self.outcome_message(http, channel_id, bet_outcome).await?.content);
+        }
+        self.render_outcomes(&bet)?.get(bet_outcome.outcome_id).cloned()
+            .ok_or(anyhow!("bet {} has no outcome {}", bet_outcome.bet_id, bet_outcome.outcome_id))
+    }
+
+    /// The bet with its recorded outcomes and the positions held by the betting crate, 
+    /// or by the bot if its resolution was undone
+    fn current_bet(&self, server: u64, bet_id: u64) -> Result<Bet> {
+        let mut bet = bet_stub(bet_id, server, &self.db.bet_outcomes(bet_id)?);
+        let wagers = match self.db.escrow(bet_id)? {
+            Some(wagers) => wagers,
+            None => self.wagers(server, bet_id)?
+        };
+        for wager in wagers {
+            if let Some(outcome) = bet.outcomes.get_mut(wager.outcome) {
+                outcome.wagers.push((wager.user, wager.amount));
+            }
+        }
+        Ok(bet)
+    }
+
+    async fn outcome_message(&self, http: &Http, channel_id: ChannelId, bet_outcome: BetOutcome) -> Result<Message> {
+        let channel_id = self.outcomes_channel(channel_id, bet_outcome.bet_id)?;
+        let msg_id = self.msg_map.get(bet_outcome)?;
+        Ok(http.get_message(channel_id, msg_id.into()).await?)
+    }
+
+    /// Retrieves the outcome description from the content of an outcome message
+    fn outcome_desc(content: &str) -> String {
+        content.lines().next().unwrap_or_default().trim_start_matches("## >").trim().to_string()
+    }
+
+    pub async fn abort_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.check_rights(&ctx, command, bet_id).await?;
+        let _guard = self.guard_bet(bet_id).await;
+        let guild_id = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        self.refund_bet(guild_id.get(), bet_id)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content("*Bet aborted, participants have been refunded.*")
+            )
+        ).await?;
+        self.delete_outcomes(&ctx.http, command.channel_id, bet_id).await?;
+        self.update_leaderboards(&ctx.http, guild_id).await?;
+        Ok(())
+    }
+
+    /// Gives everyone their stake back and marks the bet as aborted
+    fn refund_bet(&self, server: u64, bet_id: u64) -> Result<()> {
+        if let Some(wagers) = self.db.escrow(bet_id)? {
+            // the bet was undone, its stakes are held by the bot
+            for wager in wagers {
+                self.adjust_balance(server, wager.user, wager.amount as i64)?;
+            }
+            self.db.remove_escrow(bet_id)?;
+        } else {
+            self.bets.abort_bet(bet_id)?;
+        }
+        if let Some(bookmaker) = self.db.bookmaker(bet_id)? {
+            self.adjust_balance(server, bookmaker.author, bookmaker.bankroll as i64)?;
+        }
+        self.void_parlays(server, bet_id)?;
+        self.db.set_bet_status(bet_id, BetStatus::Aborted)?;
+        self.db.refund_positions(bet_id)?;
+        self.db.remove_deadline(bet_id)?;
+        Ok(())
+    }
+
+    async fn delete_outcomes(&self, http: &Http, channel_id: ChannelId, bet_id: u64) -> Result<()> {
+        if self.db.is_compact_bet(bet_id)? {
+            // only the menus of the bet have to go
+            self.bet_channel(channel_id, bet_id)?.edit_message(http, bet_id, EditMessage::new().components(vec![])).await?;
+            return Ok(());
+        }
+        let channel_id = self.outcomes_channel(channel_id, bet_id)?;
+        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
+            let msg_id = self.msg_map.get(BetOutcome { bet_id, outcome_id: outcome_id as usize })?;
+            http.delete_message(channel_id, msg_id.into(), None).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the amount the user already bet on this outcome, 
+    /// or tells them they can't bet if they already bet on another outcome of the bet
+    async fn previous_bet(&self, ctx: &Context, command: &ComponentInteraction, bet_outcome: &BetOutcome) -> Result<u64> {
+        match self.bets.position(command.user.id.get(), bet_outcome.bet_id) {
+            Result::Ok(position) => {
+                if position.outcome != bet_outcome.outcome_id {
+                    command.create_response(
+                        &ctx.http, 
+                CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new().content(
+                                format!(
+                                    "You put a bet on option #{} and can only bet on one option\n-# use 🔀 Switch to move your bet", 
+                                    position.outcome+1
+                                )
+                            )
+                            .ephemeral(true)
+                        )
+                    ).await?;
+                    bail!("user tried to bet on multiple option");        
+                }
+                Ok(position.amount)
+            },
+            Err(betting::BetError::NotFound) => Ok(0),
+            Err(err) => bail!(err)
+        }
+    }
+
+    pub async fn bet_click_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user_uuid = command.user.id.get();
+        let cfg = self.config(server_uuid)?;
+        self.bets.create_account(server_uuid, user_uuid, cfg.starting_coins as u64)?;
+        let balance = self.bets.balance(server_uuid, user_uuid)?;
+        let bet_info = self.bets.bet_info(bet_outcome.bet_id)?;
+        let outcome_text = if self.db.is_compact_bet(bet_outcome.bet_id)? {
+            shorten(self.db.bet_outcomes(bet_outcome.bet_id)?.get(bet_outcome.outcome_id).ok_or(anyhow!("unknown outcome"))?, 20)
+        } else {
+            shorten(&command.message.content.trim_start_matches("## >"), 20)
+        };
+        let previous_bet = self.previous_bet(&ctx, command, &bet_outcome).await?;
+        let mut inputs = vec![
+            CreateActionRow::InputText(
+                CreateInputText::new(
+                    InputTextStyle::Short, 
+                    format!("[{} {}] Bet on: {}", previous_bet, cfg.currency, outcome_text),
+                    bet_outcome.to_string()
+                ).placeholder("100").required(true)
+            )
+        ];
+        if self.db.guess_bet(bet_outcome.bet_id)?.is_some() {
+            let previous_guess = self.db.guesses(bet_outcome.bet_id)?.get(&user_uuid).copied();
+            let mut guess_input = CreateInputText::new(InputTextStyle::Short, "Your guess", GUESS_INPUT).required(true);
+            if let Some(guess) = previous_guess {
+                guess_input = guess_input.value(guess.to_string());
+            }
+            inputs.push(CreateActionRow::InputText(guess_input));
+        }
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Modal(
+                CreateModal::new(
+                    BetAction::BetOrder, 
+                    format!("[{} {}] {}", balance, cfg.currency, shorten(&bet_info.desc, 20))
+                ).components(inputs)
+            )).await?;
+        Ok(())
+    }
+
+    pub async fn quick_bet_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome, percent: u8) -> Result<()> {
+        let _guard = self.guard_bet(bet_outcome.bet_id).await;
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        let user_uuid = command.user.id.get();
+        let cfg = self.config(server_uuid.get())?;
+        self.bets.create_account(server_uuid.get(), user_uuid, cfg.starting_coins as u64)?;
+        self.previous_bet(&ctx, command, &bet_outcome).await?;
+        let balance = self.bets.balance(server_uuid.get(), user_uuid)?;
+        let amount = balance*min(percent, 100) as u64/100;
+        if amount == 0 {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("{}% of your balance ({} {}) is not enough to bet", percent, balance, cfg.currency))
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("quick bet amount is 0");
+        }
+        if let Some(error) = self.stake_error(server_uuid.get(), &bet_outcome, user_uuid, amount, &cfg)? {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("stake refused");
+        }
+        let (acc_update, bet) = self.bets.bet_on(bet_outcome.bet_id, bet_outcome.outcome_id, user_uuid, amount)?;
+        self.record_wager(&bet, bet_outcome.outcome_id, user_uuid, amount)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(Self::bet_placed_msg(&bet, bet_outcome.outcome_id, user_uuid, amount, acc_update.balance, &cfg.currency))
+                    .ephemeral(true)
+            )
+        ).await?;
+        self.update_outcomes(&ctx.http, command.channel_id, &bet).await?;
+        self.update_leaderboards(&ctx.http, server_uuid).await
+    }
+
+    pub async fn bet_order_action(&self, ctx: Context, command: &ModalInteraction) -> Result<()> {
+        let user = command.user.id.get();
+        if let ActionRowComponent::InputText(input) = &(&command.data.components[0]).components[0] {
+            let bet_outcome = BetOutcome::try_from(input.custom_id.as_ref())?;
+            let _guard = self.guard_bet(bet_outcome.bet_id).await;
+            let amount: u64 = <Option<String> as Clone>::clone(&input.value).unwrap().parse()?;
+            let guess = command.data.components.iter()
+                .filter_map(|row| row.components.get(0))
+                .find_map(|component| match component {
+                    ActionRowComponent::InputText(input) if input.custom_id == GUESS_INPUT => input.value.clone(),
+                    _ => None
+                });
+            let guess = match guess {
+                Some(guess) => {
+                    let Some(guess) = Self::parse_number(&guess) else {
+                        command.create_response(
+                            &ctx.http, 
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new().content("Your guess must be a number").ephemeral(true)
+                            )
+                        ).await?;
+                        bail!("invalid guess");
+                    };
+                    Some(guess)
+                },
+                None => None
+            };
+            let server = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+            if let Some(error) = self.stake_error(server, &bet_outcome, user, amount, &self.config(server)?)? {
+                command.create_response(
+                    &ctx.http, 
+                    CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+                ).await?;
+                bail!("stake refused");
+            }
+            let (acc_update, bet) = self.bets.bet_on(bet_outcome.bet_id, bet_outcome.outcome_id, user, amount)?;
+            if let Some(guess) = guess {
+                self.db.set_guess(bet.bet, user, guess)?;
+                self.db.record_wager(bet.bet, user, 0, &format!("guessed {}", guess), amount, Utc::now().timestamp())?;
+            } else {
+                self.record_wager(&bet, bet_outcome.outcome_id, user, amount)?;
+            }
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(Self::bet_placed_msg(&bet, bet_outcome.outcome_id, user, amount, acc_update.balance, &self.config(bet.server)?.currency))
+                        .ephemeral(true)
+                )
+            ).await?;
+            self.update_outcomes(&ctx.http, command.channel_id, &bet).await?;
+            if let Some(guild_id) = command.guild_id {
+                self.update_leaderboards(&ctx.http, guild_id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers a command, a component or a modal
+    async fn respond(http: &Http, interaction: &Interaction, response: CreateInteractionResponse) -> Result<()> {
+        match interaction {
+            Interaction::Command(command) => command.create_response(http, response).await?,
+            Interaction::Component(command) => command.create_response(http, response).await?,
+            Interaction::Modal(command) => command.create_response(http, response).await?,
+            _ => bail!("unexpected interaction")
+        }
+        Ok(())
+    }
+
+    /// Tells the user they can't change their position, if they can't
+    async fn open_position(&self, ctx: &Context, interaction: &Interaction, bet_id: u64, user: u64) -> Result<betting::Position> {
+        let error = if !self.bets.bet_info(bet_id)?.is_open {
+            "This bet is locked, positions can't be changed anymore".to_string()
+        } else {
+            match self.bets.position(user, bet_id) {
+                Result::Ok(position) => return Ok(position),
+                Err(betting::BetError::NotFound) => "You haven't bet on this yet".to_string(),
+                Err(err) => bail!(err)
+            }
+        };
+        Self::respond(&ctx.http, interaction, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(error).ephemeral(true)
+        )).await?;
+        bail!("position of user {} on bet {} can't be changed", user, bet_id);
+    }
+
+    pub async fn withdraw_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        let server = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user = command.user.id.get();
+        let position = self.open_position(&ctx, &Interaction::Component(command.clone()), bet_id, user).await?;
+        let cfg = self.config(server)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Modal(
+                CreateModal::new(BetAction::WithdrawOrder(bet_id), format!("[{} {}] Withdraw", position.amount, cfg.currency))
+                    .components(vec![CreateActionRow::InputText(
+                        CreateInputText::new(
+                            InputTextStyle::Short, 
+                            if cfg.withdraw_penalty > 0 { 
+                                format!("Amount ({}% penalty)", cfg.withdraw_penalty) 
+                            } else { 
+                                "Amount".to_string() 
+                            },
+                            "amount"
+                        ).value(position.amount.to_string()).required(true)
+                    )])
+            )
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn withdraw_order_action(&self, ctx: Context, command: &ModalInteraction, bet_id: u64) -> Result<()> {
+        let _guard = self.guard_bet(bet_id).await;
+        let guild_id = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        let server = guild_id.get();
+        let user = command.user.id.get();
+        let position = self.open_position(&ctx, &Interaction::Modal(command.clone()), bet_id, user).await?;
+        let cfg = self.config(server)?;
+        let amount = command.data.components.iter()
+            .filter_map(|row| row.components.get(0))
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) => input.value.clone(),
+                _ => None
+            })
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .filter(|amount| *amount > 0 && *amount <= position.amount);
+        let remaining = amount.map(|amount| position.amount - amount);
+        let min_stake = self.db.stake_limits(bet_id)?.min_stake.unwrap_or(cfg.min_stake as u64);
+        let mut wagers = self.wagers(server, bet_id)?;
+        for wager in wagers.iter_mut().filter(|wager| wager.user == user) {
+            wager.amount = remaining.unwrap_or(wager.amount);
+        }
+        let error = match remaining {
+            None => Some(format!("You can withdraw between 1 and {} {}", position.amount, cfg.currency)),
+            Some(remaining) if remaining > 0 && remaining < min_stake => Some(format!(
+                "The minimum stake on this bet is {} {}, withdraw everything or leave at least that much", min_stake, cfg.currency
+            )),
+            _ => match self.db.bookmaker(bet_id)? {
+                Some(bookmaker) if !bankroll_covers(&wagers, &bookmaker.odds, bookmaker.bankroll) => 
+                    Some("The bookmaker couldn't cover the other bets anymore if you withdrew this much".to_string()),
+                _ => None
+            }
+        };
+        let (Some(amount), Some(remaining), None) = (amount, remaining, error.clone()) else {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().content(error.unwrap_or_default()).ephemeral(true)
+                )
+            ).await?;
+            bail!("invalid withdrawal");
+        };
+        let bet = self.rebuild_bet(&ctx.http, command.channel_id, server, bet_id, user, 
+            if remaining > 0 { Some((position.outcome, remaining)) } else { None }
+        ).await?;
+        let penalty = amount*cfg.withdraw_penalty as u64/100;
+        if penalty > 0 {
+            self.adjust_balance(server, user, -(penalty as i64))?;
+            self.db.add_to_pot(server, penalty as i64)?;
+        }
+        self.db.withdraw_position(bet_id, user, amount)?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "Withdrew {} {}{}, {} {} left on the bet\nnew balance: {} {}", 
+                        amount, cfg.currency, 
+                        if penalty > 0 { format!(" ({} {} penalty)", penalty, cfg.currency) } else { String::new() },
+                        remaining, cfg.currency, self.bets.balance(server, user)?, cfg.currency
+                    ))
+                    .ephemeral(true)
+            )
+        ).await?;
+        self.update_outcomes(&ctx.http, command.channel_id, &bet).await?;
+        self.update_leaderboards(&ctx.http, guild_id).await
+    }
+
+    pub async fn switch_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
+        let guild_id = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        let server = guild_id.get();
+        let user = command.user.id.get();
+        let bet_id = bet_outcome.bet_id;
+        let _guard = self.guard_bet(bet_id).await;
+        let position = self.open_position(&ctx, &Interaction::Component(command.clone()), bet_id, user).await?;
+        let mut wagers = self.wagers(server, bet_id)?;
+        for wager in wagers.iter_mut().filter(|wager| wager.user == user) {
+            wager.outcome = bet_outcome.outcome_id;
+        }
+        let error = if position.outcome == bet_outcome.outcome_id {
+            Some("Your bet is already on this option".to_string())
+        } else {
+            match self.db.bookmaker(bet_id)? {
+                Some(bookmaker) if !bankroll_covers(&wagers, &bookmaker.odds, bookmaker.bankroll) => 
+                    Some("The bookmaker can't cover your bet on this option".to_string()),
+                _ => None
+            }
+        };
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("invalid switch");
+        }
+        let bet = self.rebuild_bet(&ctx.http, command.channel_id, server, bet_id, user, 
+            Some((bet_outcome.outcome_id, position.amount))
+        ).await?;
+        let desc = bet.outcomes[bet_outcome.outcome_id].desc.clone();
+        self.db.switch_position(bet_id, user, bet_outcome.outcome_id, &desc)?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("Moved your {} {} to:\n## > {}", position.amount, self.config(server)?.currency, desc))
+                    .ephemeral(true)
+            )
+        ).await?;
+        self.update_outcomes(&ctx.http, command.channel_id, &bet).await
+    }
+
+    /// The betting crate can't take coins out of a position, so the bet is aborted and created again 
+    /// with the same positions except for the user, whose position is replaced by the given (outcome, amount)
+    async fn rebuild_bet(
+        &self, http: &Http, channel_id: ChannelId, server: u64, bet_id: u64, user: u64, position: Option<(usize, u64)>
+    ) -> Result<Bet> {
+        let info = self.bets.bet_info(bet_id)?;
+        let outcomes = self.outcome_descs(http, channel_id, bet_id).await?;
+        let wagers = self.wagers(server, bet_id)?.into_iter()
+            .filter(|wager| wager.user != user)
+            .chain(position.map(|(outcome, amount)| Wager { user, outcome, amount }))
+            .collect::<Vec<_>>();
+        self.recreate_bet(server, bet_id, info.desc, &outcomes, &wagers)
+    }
+
+    /// Aborts the bet and creates it again with the given description, outcomes and positions,
+    /// must be called with the bet guarded so no position is taken in between
+    fn recreate_bet(&self, server: u64, bet_id: u64, desc: String, outcomes: &Vec<String>, wagers: &[Wager]) -> Result<Bet> {
+        let info = self.bets.bet_info(bet_id)?;
+        self.bets.abort_bet(bet_id)?;
+        self.bets.create_bet(bet_id, server, info.author, desc, outcomes)?;
+        let mut bet = bet_stub(bet_id, server, outcomes);
+        for wager in wagers {
+            match self.bets.bet_on(bet_id, wager.outcome, wager.user, wager.amount) {
+                Result::Ok((_, updated)) => bet = updated,
+                Err(why) => {
+                    // the stake was refunded by the abort, the other positions are still replayed
+                    warn!(target: "betting-bot", "Couldn't place back the {} of <@{}> on bet {}: {}", wager.amount, wager.user, bet_id, why);
+                    self.db.withdraw_position(bet_id, wager.user, wager.amount)?;
+                }
+            }
+        }
+        Ok(bet)
+    }
+
+    /// Descriptions of the outcomes of the bet, read from their messages for the bets that predate bet_outcomes
+    async fn outcome_descs(&self, http: &Http, channel_id: ChannelId, bet_id: u64) -> Result<Vec<String>> {
+        let outcomes = self.db.bet_outcomes(bet_id)?;
+        if !outcomes.is_empty() {
+            return Ok(outcomes);
+        }
+        let mut outcomes = Vec::new();
+        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
+            let message = self.outcome_message(http, channel_id, BetOutcome { bet_id, outcome_id: outcome_id as usize }).await?;
+            outcomes.push(Self::outcome_desc(&message.content));
+        }
+        Ok(outcomes)
+    }
+
+    /// Outcomes can only be added to bets that are resolved by picking the winning outcomes
+    fn can_add_outcomes(&self, bet_id: u64) -> Result<bool> {
+        Ok(!self.resolved_by_number(bet_id)? && self.db.bookmaker(bet_id)?.is_none())
+    }
+
+    pub async fn edit_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.check_rights(&ctx, command, bet_id).await?;
+        let info = self.bets.bet_info(bet_id)?;
+        let mut inputs = vec![CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Paragraph, "Description", DESC_INPUT).value(info.desc).required(true)
+        )];
+        // a closest guess bet has a single outcome that can't be renamed, 
+        // and the outcomes of a number bet are named after its line
+        if self.db.guess_bet(bet_id)?.is_none() && self.db.number_bet(bet_id)?.is_none() {
+            let outcomes = self.outcome_descs(&ctx.http, command.channel_id, bet_id).await?;
+            inputs.push(CreateActionRow::InputText(
+                CreateInputText::new(
+                    InputTextStyle::Paragraph, 
+                    if self.can_add_outcomes(bet_id)? { "Outcomes, one per line, add lines for more" } else { "Outcomes, one per line" },
+                    MORE_OUTCOMES_INPUT
+                ).value(outcomes.join("\n")).required(true)
+            ));
+        }
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Modal(CreateModal::new(BetAction::EditOrder(bet_id), "Edit the bet").components(inputs))
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn edit_order_action(&self, ctx: Context, command: &ModalInteraction, bet_id: u64) -> Result<()> {
+        let _guard = self.guard_bet(bet_id).await;
+        let server = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let input = |id: &str| command.data.components.iter()
+            .filter_map(|row| row.components.get(0))
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) if input.custom_id == id => input.value.clone(),
+                _ => None
+            });
+        let desc = input(DESC_INPUT).unwrap_or_default();
+        let compact = self.db.is_compact_bet(bet_id)?;
+        let current = self.outcome_descs(&ctx.http, command.channel_id, bet_id).await?;
+        let outcomes: Vec<String> = match input(MORE_OUTCOMES_INPUT) {
+            Some(outcomes) => outcomes.lines().map(|outcome| outcome.trim().to_string()).filter(|outcome| !outcome.is_empty()).collect(),
+            None => current.clone()
+        };
+        let wagers = self.wagers(server, bet_id)?;
+        // bettors and parlay legs on an outcome keep what they picked, so it can't be renamed under them
+        let parlay_outcomes = self.db.parlay_outcomes(bet_id)?;
+        let renamed_with_stakes = current.iter().zip(&outcomes).enumerate().find(|(i, (old, new))| 
+            old != new && (wagers.iter().any(|wager| wager.outcome == *i) || parlay_outcomes.contains(i))
+        );
+        let error = if !self.has_rights(&command.user, command.member.as_ref(), bet_id)? {
+            Some("Only the bet author or admins can perform this action".to_string())
+        } else if !self.bets.bet_info(bet_id)?.is_open {
+            Some("This bet is locked, it can't be edited anymore".to_string())
+        } else if outcomes.len() < current.len() {
+            Some("Outcomes can't be removed, abort the bet instead".to_string())
+        } else if outcomes.len() > current.len() && !self.can_add_outcomes(bet_id)? {
+            Some("Outcomes can't be added to this kind of bet".to_string())
+        } else if outcomes != current && self.db.number_bet(bet_id)?.is_some() {
+            Some("The outcomes of a number bet follow its line, they can't be edited".to_string())
+        } else if let Some((_, (old, _))) = renamed_with_stakes {
+            Some(format!("'{}' already has bets on it, it can't be renamed anymore", shorten(old, 30)))
+        } else if compact && !Self::fits_in_message(
+            &desc, &outcomes, self.db.deadline(bet_id)?, &self.db.stake_limits(bet_id)?, &self.config(server)?.currency
+        ) {
+            Some("The bet wouldn't fit in a single message anymore, shorten it".to_string())
+        } else if current.len() > 1 {
+            Self::bet_form_error(&desc, &outcomes)
+        } else if desc.trim().is_empty() {
+            Some("The bet needs a description".to_string())
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error.clone()).ephemeral(true))
+            ).await?;
+            bail!("invalid edit of bet {}: {}", bet_id, error);
+        }
+        let previous_desc = self.bets.bet_info(bet_id)?.desc;
+        let bet = self.recreate_bet(server, bet_id, desc.clone(), &outcomes, &wagers)?;
+        self.db.set_bet_desc(bet_id, &desc)?;
+        self.db.set_bet_outcomes(bet_id, &outcomes)?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content("✏️ Bet edited").ephemeral(true)
+            )
+        ).await?;
+        if let Some((_, thread)) = self.db.bet_thread(bet_id)?.filter(|_| desc != previous_desc) {
+            if let Err(why) = ChannelId::new(thread).edit_thread(&ctx.http, EditThread::new().name(shorten(&desc, 100))).await {
+                warn!(target: "betting-bot", "Couldn't rename the thread of bet {}: {}", bet_id, why);
+            }
+        }
+        // the header and outcomes of a compact bet are all rewritten by update_outcomes
+        if !compact {
+            let currency = self.config(server)?.currency;
+            let mut bet_msg = self.bet_message(&ctx.http, command.channel_id, bet_id).await?;
+            bet_msg.edit(&ctx.http, EditMessage::new().content(Self::bet_header(
+                &desc, self.db.deadline(bet_id)?, &self.db.stake_limits(bet_id)?, &currency
+            ))).await?;
+            let displays = self.render_outcomes(&bet)?;
+            for (outcome_id, display) in displays.iter().enumerate().skip(current.len()) {
+                let bet_outcome = BetOutcome { bet_id, outcome_id };
+                let outcome_msg = self.outcomes_channel(command.channel_id, bet_id)?.send_message(&ctx.http, 
+                    CreateMessage::new().content(display).components(Self::outcome_components(bet_outcome.clone(), &currency))
+                ).await?;
+                self.msg_map.insert(bet_outcome, outcome_msg.id.get())?;
+            }
+        }
+        self.update_outcomes(&ctx.http, command.channel_id, &bet).await
+    }
+
+    /// Why the stake can't be placed, if it can't
+    fn stake_error(&self, server: u64, bet_outcome: &BetOutcome, user: u64, amount: u64, cfg: &Config) -> Result<Option<String>> {
+        let limits = self.db.stake_limits(bet_outcome.bet_id)?;
+        let min_stake = limits.min_stake.unwrap_or(cfg.min_stake as u64);
+        let max_stake = limits.max_stake.unwrap_or(cfg.max_stake as u64);
+        let max_pool_share = limits.max_pool_share.unwrap_or(cfg.max_pool_share) as u64;
+        let wagers = self.wagers(server, bet_outcome.bet_id)?;
+        let previous: u64 = wagers.iter().filter(|wager| wager.user == user).map(|wager| wager.amount).sum();
+        let pool: u64 = wagers.iter().map(|wager| wager.amount).sum::<u64>() + amount;
+        // limits apply to the whole position of the user on the bet
+        let position = previous + amount;
+        if position < min_stake {
+            return Ok(Some(format!("The minimum stake on this bet is {} {}", min_stake, cfg.currency)));
+        }
+        if max_stake > 0 && position > max_stake {
+            return Ok(Some(format!(
+                "The maximum stake on this bet is {} {}, you can only add {} {}", 
+                max_stake, cfg.currency, max_stake.saturating_sub(previous), cfg.currency
+            )));
+        }
+        // the pool counts as at least a fresh account so the first bettors aren't stuck
+        if max_pool_share < 100 && position*100 > max_pool_share*max(pool, cfg.starting_coins as u64) {
+            return Ok(Some(format!(
+                "A single user can't hold more than {}% of the pool, try a smaller stake", max_pool_share
+            )));
+        }
+        if let Some(bookmaker) = self.db.bookmaker(bet_outcome.bet_id)? {
+            if user == bookmaker.author {
+                return Ok(Some("You're the bookmaker of this bet, you can't bet on it".to_string()));
+            }
+            let capacity = odds_capacity(&wagers, &bookmaker.odds, bookmaker.bankroll, bet_outcome.outcome_id);
+            if amount > capacity {
+                return Ok(Some(format!("The bookmaker can only cover {} {} more on this outcome", capacity, cfg.currency)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn record_wager(&self, bet: &Bet, outcome_id: usize, user: u64, amount: u64) -> Result<()> {
+        self.db.record_wager(bet.bet, user, outcome_id, &bet.outcomes[outcome_id].desc, amount, Utc::now().timestamp())
+    }
+
+    fn bet_placed_msg(bet: &Bet, outcome_id: usize, user: u64, amount: u64, balance: u64, currency: &str) -> String {
+        let total: u64 = bet.outcomes[outcome_id].wagers
+            .iter().filter(|(u, _)| *u == user).map(|(_, a)| a).sum();
+        format!(
+            "Succesfully bet {} {} (total {} {}) on:\n## > {}\nnew balance: {} {}", 
+            amount, currency, total, currency, bet.outcomes[outcome_id].desc, balance, currency
+        )
+    }
+
+    /// Parses a number typed by a user, accepting decimal commas
+    fn parse_number(value: &str) -> Option<f64> {
+        value.trim().replace(",", ".").parse::<f64>().ok().filter(|number| number.is_finite())
+    }
+
+    /// Content of the outcome messages of the bet
+    fn render_outcomes(&self, bet: &Bet) -> Result<Vec<String>> {
+        let currency = self.config(bet.server)?.currency;
+        if self.db.guess_bet(bet.bet)?.is_some() {
+            let guesses = self.db.guesses(bet.bet)?;
+            return Ok(vec![guesses_display(guesses.into_iter().map(|(user, guess)| {
+                let stake = bet.outcomes[0].wagers.iter().filter(|(u, _)| *u == user).map(|(_, a)| a).sum();
+                (user, guess, stake)
+            }).collect(), &currency)]);
+        }
+        let fixed_odds = self.db.bookmaker(bet.bet)?.map(|bookmaker| bookmaker.odds);
+        Ok(outcomes_display(bet, fixed_odds.as_deref(), &currency))
+    }
+
+    async fn update_outcomes(&self, http: &Http, channel_id: ChannelId, bet: &Bet) -> Result<()> {
+        if self.db.is_compact_bet(bet.bet)? {
+            // a single edit, the menus are sent again so that they don't keep the last pick
+            let outcomes: Vec<String> = bet.outcomes.iter().map(|outcome| outcome.desc.clone()).collect();
+            self.bet_channel(channel_id, bet.bet)?.edit_message(http, bet.bet, 
+                EditMessage::new().content(self.compact_content(bet)?).components(Self::compact_components(bet.bet, &outcomes))
+            ).await?;
+            return Ok(());
+        }
+        let displays = self.render_outcomes(bet)?;
+        for (i, outcome) in displays.iter().enumerate() {
+            let mut msg = self.outcome_message(http, channel_id, BetOutcome { bet_id: bet.bet, outcome_id: i }).await?;
+            msg.edit(http, EditMessage::new().content(outcome)).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn resolve_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
+        let Some(guild_id) = command.guild_id else {
+            bail!("Couldn't find the guild id");
+        };
+        self.check_rights(&ctx, command, bet_outcome.bet_id).await?;
+        let _guard = self.guard_bet(bet_outcome.bet_id).await;
+        let cfg = self.config(guild_id.get())?;
+        let fixed_odds = self.db.bookmaker(bet_outcome.bet_id)?.is_some();
+        let outcome_content = self.outcome_content(&ctx.http, command.channel_id, guild_id.get(), bet_outcome.clone()).await?;
+        let settlement = self.settle(guild_id.get(), bet_outcome.bet_id, &[(bet_outcome.outcome_id, 1)])?;
+        let total: u64 = settlement.payouts.iter().map(|(_, payout)| payout).sum();
+        // Everyone wins a little activity bonus
+        let income_accounts = self.pay_income(guild_id.get(), cfg.income as u64)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "## Bet resolved as\n{}\n{} {} is {}.{}\n-# everyone also wins {}`{}` for activity bonus !", 
+                        outcome_content, total.saturating_sub(settlement.rake), cfg.currency, 
+                        if fixed_odds { "paid to the winners at fixed odds" } else { "shared between the winners" },
+                        rake_display(settlement.rake, &cfg.currency) + &parlays_display(&settlement.parlays, &cfg.currency), cfg.income, cfg.currency
+                    ))
+                    .components(Self::undo_components(bet_outcome.bet_id, &cfg))
+            )
+        ).await?;
+        let announcement = command.get_response(&ctx.http).await?;
+        self.track_resolution(guild_id.get(), command.channel_id.get(), announcement.id.get(), bet_outcome.bet_id, settlement, income_accounts)?;
+        self.close_bet(&ctx.http, command.channel_id, bet_outcome.bet_id).await?;
+        self.update_leaderboards(&ctx.http, guild_id).await?;
+        Ok(())
+    }
+
+    /// Pays the winners of the bet according to the weights of the winning outcomes and records the payouts
+    fn settle(&self, server: u64, bet_id: u64, weights: &[(usize, u64)]) -> Result<Settlement> {
+        let settlement = self.pay_winners(server, bet_id, weights)?;
+        self.record_settlement(server, bet_id, settlement)
+    }
+
+    /// Takes the rake from what the participants received and records the payouts
+    fn record_settlement(&self, server: u64, bet_id: u64, mut settlement: Settlement) -> Result<Settlement> {
+        let cfg = self.config(server)?;
+        let taken = take_rake(&mut settlement.diffs, &settlement.wagers, cfg.rake as u64);
+        if !taken.is_empty() {
+            self.apply_diffs(server, &taken.iter().map(|(user, share)| (*user, -(*share as i64))).collect::<Vec<_>>())?;
+            settlement.rake = taken.iter().map(|(_, share)| share).sum();
+            self.db.add_to_pot(server, settlement.rake as i64)?;
+            if cfg.undo_window > 0 {
+                self.db.set_rake(bet_id, server, settlement.rake)?;
+            }
+        }
+        settlement.parlays = self.settle_parlays(server, bet_id, &settlement.payouts)?;
+        self.db.set_bet_status(bet_id, BetStatus::Resolved)?;
+        self.db.set_payouts(bet_id, &settlement.diffs.iter()
+            .map(|(user, diff)| (*user, max(*diff, 0) as u64))
+            .collect::<Vec<_>>(), 
+            &settlement.payouts
+        )?;
+        Ok(settlement)
+    }
+
+    /// Pays the winners either through the betting crate or from the escrow of an undone resolution
+    fn pay_winners(&self, server: u64, bet_id: u64, weights: &[(usize, u64)]) -> Result<Settlement> {
+        if let Some(bookmaker) = self.db.bookmaker(bet_id)? {
+            let [(winner, _)] = weights else {
+                bail!("fixed odds bet {} can only have 1 winner", bet_id);
+            };
+            return self.pay_fixed_odds(server, bet_id, *winner, &bookmaker);
+        }
+        if let Some(wagers) = self.db.escrow(bet_id)? {
+            // the stakes are already out of the balances, only the payouts are left to give
+            let split = split_pool(&wagers, weights);
+            let diffs: Vec<(u64, i64)> = wagers.iter().zip(split.diffs)
+                .map(|(wager, (user, diff))| (user, diff + wager.amount as i64))
+                .collect();
+            self.apply_diffs(server, &diffs)?;
+            self.db.remove_escrow(bet_id)?;
+            return Ok(Settlement { diffs, payouts: split.payouts, wagers, rake: 0, parlays: vec![] });
+        }
+        let wagers = self.wagers(server, bet_id)?;
+        if let [(outcome_id, _)] = weights {
+            let diffs: Vec<(u64, i64)> = self.bets.resolve(bet_id, *outcome_id)?
+                .into_iter().map(|acc| (acc.user, acc.diff)).collect();
+            let total = diffs.iter().map(|(_, diff)| diff).sum::<i64>();
+            return Ok(Settlement { diffs, payouts: vec![(*outcome_id, total as u64)], wagers, rake: 0, parlays: vec![] });
+        }
+        // the betting crate can only pay a single winner, positions are refunded by an abort 
+        // and the split is applied on top of it, the resolution itself is recorded by record_settlement
+        let split = split_pool(&wagers, weights);
+        self.bets.abort_bet(bet_id)?;
+        self.pay_after_abort(server, bet_id, &split.diffs)?;
+        let diffs = wagers.iter().zip(split.diffs)
+            .map(|(wager, (user, diff))| (user, diff + wager.amount as i64))
+            .collect();
+        Ok(Settlement { diffs, payouts: split.payouts, wagers, rake: 0, parlays: vec![] })
+    }
+
+    /// Applies the balance changes of a bet whose stakes were just refunded by an abort, all or nothing.
+    /// If they can't be applied everyone keeps their refund and the bet is recorded as aborted
+    fn pay_after_abort(&self, server: u64, bet_id: u64, diffs: &[(u64, i64)]) -> Result<()> {
+        if let Err(why) = self.apply_diffs(server, diffs) {
+            if let Some(bookmaker) = self.db.bookmaker(bet_id)? {
+                self.adjust_balance(server, bookmaker.author, bookmaker.bankroll as i64)?;
+            }
+            self.void_parlays(server, bet_id)?;
+            self.db.set_bet_status(bet_id, BetStatus::Aborted)?;
+            self.db.refund_positions(bet_id)?;
+            bail!("couldn't pay bet {}, its stakes were refunded instead: {}", bet_id, why);
+        }
+        Ok(())
+    }
+
+    /// Pays the closest guesses of the bet, from the escrow if the resolution was undone
+    fn pay_guesses(&self, server: u64, bet_id: u64, truth: f64, payout: GuessPayout) -> Result<Settlement> {
+        let guesses = self.db.guesses(bet_id)?;
+        // everyone is on the same outcome, the stakes are released and the split is paid by hand
+        let (wagers, refunded) = self.release_stakes(server, bet_id)?;
+        let diffs: Vec<(u64, i64)> = wagers.iter().zip(guess_split(&wagers, &guesses, truth, payout))
+            .map(|(wager, (user, diff))| (user, diff + wager.amount as i64))
+            .collect();
+        self.pay_received(server, bet_id, &wagers, &diffs, refunded)?;
+        let total = wagers.iter().map(|wager| wager.amount).sum();
+        self.record_settlement(server, bet_id, Settlement { diffs, payouts: vec![(0, total)], wagers, rake: 0, parlays: vec![] })
+    }
+
+    /// Pays the winners at the fixed odds, the bookmaker gets the rest of the pool and of their bankroll
+    fn pay_fixed_odds(&self, server: u64, bet_id: u64, winner: usize, bookmaker: &Bookmaker) -> Result<Settlement> {
+        let (wagers, refunded) = self.release_stakes(server, bet_id)?;
+        let mut diffs = fixed_odds_payouts(&wagers, &bookmaker.odds, winner);
+        let total: i64 = wagers.iter().map(|wager| wager.amount as i64).sum();
+        let paid: i64 = diffs.iter().map(|(_, diff)| diff).sum();
+        let bookmaker_return = bookmaker.bankroll as i64 + total - paid;
+        diffs.push((bookmaker.author, bookmaker_return));
+        self.pay_received(server, bet_id, &wagers, &diffs, refunded)?;
+        Ok(Settlement { diffs, payouts: vec![(winner, paid as u64)], wagers, rake: 0, parlays: vec![] })
+    }
+
+    /// Frees the stakes of a bet that is paid by hand, returns the wagers and whether their stake was refunded
+    /// (it's not if the resolution was undone, the stakes are then held in escrow until pay_received)
+    fn release_stakes(&self, server: u64, bet_id: u64) -> Result<(Vec<Wager>, bool)> {
+        if let Some(wagers) = self.db.escrow(bet_id)? {
+            return Ok((wagers, false));
+        }
+        let wagers = self.wagers(server, bet_id)?;
+        self.bets.abort_bet(bet_id)?;
+        Ok((wagers, true))
+    }
+
+    /// Gives everyone what they received, minus the stakes that were already refunded, all or nothing
+    fn pay_received(&self, server: u64, bet_id: u64, wagers: &[Wager], received: &[(u64, i64)], refunded: bool) -> Result<()> {
+        if !refunded {
+            self.apply_diffs(server, received)?;
+            return self.db.remove_escrow(bet_id);
+        }
+        let transfers: Vec<(u64, i64)> = received.iter().copied()
+            .chain(wagers.iter().map(|wager| (wager.user, -(wager.amount as i64))))
+            .collect();
+        self.pay_after_abort(server, bet_id, &transfers)
+    }
+
+    fn undo_components(bet_id: u64, cfg: &Config) -> Vec<CreateActionRow> {
+        if cfg.undo_window == 0 {
+            return vec![];
+        }
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(BetAction::Undo(bet_id)).label("↩️ Undo").style(ButtonStyle::Secondary)
+        ])]
+    }
+
+    /// Remembers what the resolution changed so it can be undone during the undo window
+    fn track_resolution(
+        &self, guild: u64, channel: u64, message: u64, bet_id: u64, settlement: Settlement, income_accounts: Vec<u64>
+    ) -> Result<()> {
+        let cfg = self.config(guild)?;
+        if cfg.undo_window == 0 {
+            return Ok(());
+        }
+        self.db.add_resolution(&Resolution {
+            bet: bet_id, guild, channel, message,
+            expires: Utc::now().timestamp() + cfg.undo_window as i64*60,
+            income: cfg.income as u64,
+            income_accounts,
+            diffs: settlement.diffs,
+            wagers: settlement.wagers,
+        })
+    }
+
+    /// Gives the activity bonus to every account of the guild, returns who got it
+    fn pay_income(&self, server: u64, income: u64) -> Result<Vec<u64>> {
+        let accounts: Vec<u64> = self.bets.accounts(server)?.into_iter().map(|acc| acc.user).collect();
+        self.apply_diffs(server, &accounts.iter().map(|user| (*user, income as i64)).collect::<Vec<_>>())?;
+        Ok(accounts)
+    }
+
+    pub async fn undo_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.check_rights(&ctx, command, bet_id).await?;
+        let _guard = self.guard_bet(bet_id).await;
+        let Some(resolution) = self.db.resolution(bet_id)?.filter(|res| res.expires > Utc::now().timestamp()) else {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("This resolution is final and can't be undone anymore")
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("resolution of bet {} is final", bet_id);
+        };
+        // everything the resolution gave is taken back, if someone already spent it the resolution stays
+        let clawback: Vec<(u64, i64)> = resolution.diffs.iter().map(|(user, diff)| (*user, -diff))
+            .chain(resolution.income_accounts.iter().map(|user| (*user, -(resolution.income as i64))))
+            .chain(self.parlay_payouts(bet_id)?.into_iter().map(|(user, payout)| (user, -(payout as i64))))
+            .collect();
+        // the whole pool goes back in escrow, the rake with it, so it can't have been distributed either
+        let rake = self.db.rake(bet_id)?.unwrap_or(0);
+        let taken = self.db.add_to_pot(resolution.guild, -(rake as i64)).and_then(|_| 
+            self.apply_diffs(resolution.guild, &clawback).or_else(|why| {
+                self.db.add_to_pot(resolution.guild, rake as i64)?;
+                Err(why)
+            })
+        );
+        if let Err(why) = taken {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("This resolution can't be undone, what it paid isn't available anymore\n-# {}", why))
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("can't undo the resolution of bet {}: {}", bet_id, why);
+        }
+        self.db.remove_rake(bet_id)?;
+        self.unsettle_parlays(bet_id)?;
+        self.db.set_escrow(bet_id, &resolution.wagers)?;
+        self.db.set_bet_status(bet_id, BetStatus::Locked)?;
+        self.db.clear_payouts(bet_id)?;
+        self.db.remove_resolution(bet_id)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("*Resolution undone by <@{}>, the bet is locked again.*", command.user.id))
+                    .components(vec![])
+            )
+        ).await?;
+        let channel_id = ChannelId::new(resolution.channel);
+        let outcomes = self.lock_outcomes(&ctx.http, channel_id, bet_id).await?;
+        let mut bet_msg = self.bet_message(&ctx.http, channel_id, bet_id).await?;
+        let bet_msg_content = bet_msg.content.clone();
+        let bet_msg_content = match bet_msg_content.split_once("\n") {
+            Some((first, rest)) if first.starts_with("*Resolved") => rest.to_string(),
+            _ => bet_msg_content
+        };
+        bet_msg.edit(&ctx.http, 
+            EditMessage::new().content(bet_msg_content).components(self.locked_components(bet_id, &outcomes)?)
+        ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(resolution.guild)).await?;
+        Ok(())
+    }
+
+    /// Removes the Undo button of a resolution past its undo window
+    pub async fn finalize_resolution(&self, http: &Http, channel_id: ChannelId, msg_id: u64, bet_id: u64) -> Result<()> {
+        let _guard = self.guard_bet(bet_id).await;
+        self.db.remove_resolution(bet_id)?;
+        self.db.remove_rake(bet_id)?;
+        let mut message = http.get_message(channel_id, msg_id.into()).await?;
+        message.edit(http, EditMessage::new().components(vec![])).await?;
+        Ok(())
+    }
+
+    /// Marks the bet as resolved and removes all of its buttons
+    async fn close_bet(&self, http: &Http, channel_id: ChannelId, bet_id: u64) -> Result<()> {
+        let mut bet_msg = self.bet_message(http, channel_id, bet_id).await?;
+        let bet_msg_content = bet_msg.content.clone();
+        bet_msg.edit(http, 
+            EditMessage::new()
+                .content(format!("*Resolved {}*\n{}", Local::now().format("%d/%m/%Y"), bet_msg_content))
+                .components(vec![])
+        ).await?;
+        if self.db.is_compact_bet(bet_id)? {
+            return Ok(());
+        }
+        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
+            let mut message = self.outcome_message(http, channel_id, BetOutcome { bet_id, outcome_id: outcome_id as usize }).await?;
+            message.edit(http, EditMessage::new().components(vec![])).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn parlay_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let user = command.user.id.get();
+        let amount = command.data.options.iter().find(|opt| opt.name == "amount")
+            .and_then(|opt| opt.value.as_i64())
+            .ok_or(anyhow!("missing amount"))? as u64;
+        let cfg = self.config(guild)?;
+        self.bets.create_account(guild, user, cfg.starting_coins as u64)?;
+        let mut options = self.db.open_bet_outcomes(guild)?;
+        // a select menu can't hold more than 25 options
+        options.truncate(25);
+        let error = self.flat_stake_error(guild, user, amount, &cfg)?.or_else(|| 
+            if options.iter().map(|(bet, ..)| bet).unique().count() < 2 {
+                Some("There must be at least 2 open bets to make a parlay".to_string())
+            } else {
+                None
+            }
+        );
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("invalid parlay");
+        }
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "Pick one outcome on each bet of your {} {} parlay, it only pays if they all win", amount, cfg.currency
+                    ))
+                    .components(vec![CreateActionRow::SelectMenu(
+                        CreateSelectMenu::new(BetAction::ParlayOrder(amount), CreateSelectMenuKind::String { 
+                            options: options.iter().map(|(bet, desc, outcome, outcome_desc)| 
+                                CreateSelectMenuOption::new(
+                                    shorten(&format!("{} → {}", outcome_desc, desc), 100), 
+                                    BetOutcome { bet_id: *bet, outcome_id: *outcome }.to_string()
+                                )
+                            ).collect()
+                        })
+                        .placeholder("🎰 Legs of the parlay")
+                        .min_values(2)
+                        .max_values(options.len() as u8)
+                    )])
+                    .ephemeral(true)
+            )
+        ).await?;
+        Ok(())
+    }
+
+    /// Why the user can't stake this amount on a parlay or a challenge, if they can't
+    fn flat_stake_error(&self, guild: u64, user: u64, amount: u64, cfg: &Config) -> Result<Option<String>> {
+        let balance = self.bets.balance(guild, user)?;
+        Ok(if amount > balance {
+            Some(format!("You only have {} {} available", balance, cfg.currency))
+        } else if amount < cfg.min_stake as u64 {
+            Some(format!("The minimum stake is {} {}", cfg.min_stake, cfg.currency))
+        } else if cfg.max_stake > 0 && amount > cfg.max_stake as u64 {
+            Some(format!("The maximum stake is {} {}", cfg.max_stake, cfg.currency))
+        } else {
+            None
+        })
+    }
+
+    pub async fn parlay_order_action(&self, ctx: Context, command: &ComponentInteraction, amount: u64) -> Result<()> {
+        let guild_id = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        let guild = guild_id.get();
+        let user = command.user.id.get();
+        let ComponentInteractionDataKind::StringSelect { values } = &command.data.kind else {
+            bail!("expected a string select");
+        };
+        let picks = values.iter()
+            .map(|value| BetOutcome::try_from(value.as_str()))
+            .collect::<Result<Vec<_>>>()?;
+        let cfg = self.config(guild)?;
+        let open_outcomes = self.db.open_bet_outcomes(guild)?;
+        let legs: Vec<_> = picks.iter().filter_map(|pick| open_outcomes.iter().find(
+            |(bet, _, outcome, _)| *bet == pick.bet_id && *outcome == pick.outcome_id
+        )).collect();
+        let error = if picks.iter().map(|pick| pick.bet_id).unique().count() < picks.len() {
+            Some("A parlay can only have one outcome per bet".to_string())
+        } else if legs.len() < picks.len() {
+            Some("Some of these bets are not open anymore".to_string())
+        } else {
+            self.flat_stake_error(guild, user, amount, &cfg)?
+        };
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("invalid parlay");
+        }
+        self.adjust_balance(guild, user, -(amount as i64))?;
+        self.db.add_parlay(guild, user, amount, 
+            &legs.iter().map(|(bet, _, outcome, _)| (*bet, *outcome)).collect::<Vec<_>>(), 
+            Utc::now().timestamp()
+        )?;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(parlay_display(
+                        amount, &legs.iter().map(|(_, desc, _, outcome_desc)| (desc.as_str(), outcome_desc.as_str())).collect::<Vec<_>>(),
+                        &cfg.currency
+                    ))
+                    .components(vec![])
+            )
+        ).await?;
+        self.update_leaderboards(&ctx.http, guild_id).await
+    }
+
+    /// Fixes the odds of the parlay legs on the bet as it gets locked
+    fn lock_parlay_odds(&self, bet_id: u64) -> Result<()> {
+        if self.db.parlays_of_bet(bet_id)?.is_empty() {
+            return Ok(());
+        }
+        let odds = match self.db.bookmaker(bet_id)? {
+            Some(bookmaker) => bookmaker.odds,
+            None => pool_odds(
+                &self.wagers(self.bets.bet_info(bet_id)?.server, bet_id)?, self.bets.outcomes_of_bet(bet_id)?.len()
+            )
+        };
+        self.db.set_leg_odds(bet_id, &odds)
+    }
+
+    /// Settles the parlay legs on the resolved bet and pays the parlays that won all of their legs, 
+    /// returns the (user, payout) of these
+    fn settle_parlays(&self, server: u64, bet_id: u64, payouts: &[(usize, u64)]) -> Result<Vec<(u64, u64)>> {
+        self.db.set_leg_results(bet_id, &outcome_shares(payouts))?;
+        let mut won = Vec::new();
+        for parlay in self.db.parlays_of_bet(bet_id)? {
+            if parlay.status != ParlayStatus::Open {
+                continue;
+            }
+            if parlay.legs.iter().any(|leg| leg.share.is_some_and(|share| share <= 0.)) {
+                self.db.set_parlay_status(parlay.id, ParlayStatus::Lost, Some(0))?;
+            } else if parlay.legs.iter().all(|leg| leg.share.is_some()) {
+                let payout = parlay_payout(parlay.amount, &parlay.legs.iter()
+                    .map(|leg| (leg.odds.unwrap_or(1.), leg.share.unwrap_or_default()))
+                    .collect::<Vec<_>>()
+                );
+                self.adjust_balance(server, parlay.user, payout as i64)?;
+                self.db.set_parlay_status(parlay.id, ParlayStatus::Won, Some(payout))?;
+                won.push((parlay.user, payout));
+            }
+        }
+        Ok(won)
+    }
+
+    /// What the parlays that this resolution made win were paid
+    fn parlay_payouts(&self, bet_id: u64) -> Result<Vec<(u64, u64)>> {
+        Ok(self.db.parlays_of_bet(bet_id)?.into_iter().filter_map(|parlay| match (parlay.status, parlay.payout) {
+            (ParlayStatus::Won, Some(payout)) => Some((parlay.user, payout)),
+            _ => None
+        }).collect())
+    }
+
+    /// Reverts the settlement of the parlay legs on the bet after its resolution was undone, 
+    /// their payouts were already taken back with the rest of the resolution
+    fn unsettle_parlays(&self, bet_id: u64) -> Result<()> {
+        self.db.clear_leg_results(bet_id)?;
+        for parlay in self.db.parlays_of_bet(bet_id)? {
+            if parlay.status == ParlayStatus::Void {
+                continue;
+            }
+            // the parlay may still have lost on another bet
+            if parlay.legs.iter().any(|leg| leg.share.is_some_and(|share| share <= 0.)) {
+                self.db.set_parlay_status(parlay.id, ParlayStatus::Lost, Some(0))?;
+            } else {
+                self.db.set_parlay_status(parlay.id, ParlayStatus::Open, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Refunds the undecided parlays with a leg on the aborted bet
+    fn void_parlays(&self, server: u64, bet_id: u64) -> Result<()> {
+        for parlay in self.db.parlays_of_bet(bet_id)? {
+            if parlay.status == ParlayStatus::Open {
+                self.adjust_balance(server, parlay.user, parlay.amount as i64)?;
+                self.db.set_parlay_status(parlay.id, ParlayStatus::Void, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn challenge_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let guild = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let challenger = command.user.id.get();
+        let options = &command.data.options;
+        let opponent = options.iter().find(|opt| opt.name == "user")
+            .and_then(|opt| opt.value.as_user_id())
+            .ok_or(anyhow!("missing user"))?
+            .get();
+        let amount = options.iter().find(|opt| opt.name == "amount")
+            .and_then(|opt| opt.value.as_i64())
+            .ok_or(anyhow!("missing amount"))? as u64;
+        let claim = options.iter().find(|opt| opt.name == "claim")
+            .and_then(|opt| opt.value.as_str())
+            .ok_or(anyhow!("missing claim"))?
+            .trim()
+            .to_string();
+        let expires_in = options.iter().find(|opt| opt.name == "expires_in")
+            .and_then(|opt| opt.value.as_i64())
+            .unwrap_or(CHALLENGE_EXPIRY);
+        let cfg = self.config(guild)?;
+        self.bets.create_account(guild, challenger, cfg.starting_coins as u64)?;
+        let error = if opponent == challenger {
+            Some("You can't challenge yourself".to_string())
+        } else {
+            self.flat_stake_error(guild, challenger, amount, &cfg)?
+        };
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("invalid challenge");
+        }
+        let mut challenge = Challenge {
+            id: 0, guild, channel: command.channel_id.get(), challenger, opponent, amount, claim, 
+            status: ChallengeStatus::Pending, expires: Utc::now().timestamp() + expires_in*60,
+            challenger_vote: None, opponent_vote: None, winner: None, payout: None
+        };
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(challenge_display(&challenge, &cfg.currency))
+                    .components(self.challenge_components(&ctx.http, &challenge).await?)
+            )
+        ).await?;
+        challenge.id = command.get_response(&ctx.http).await?.id.get();
+        self.db.add_challenge(&challenge)
+    }
+
+    /// Accept/Decline while pending, a button for each side once accepted and arbitration buttons once disputed,
+    /// admins can pick the winner or refund both sides of an accepted challenge too
+    async fn challenge_components(&self, http: &Http, challenge: &Challenge) -> Result<Vec<CreateActionRow>> {
+        let (vote, mut row) = match challenge.status {
+            ChallengeStatus::Pending => return Ok(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(BetAction::ChallengeAccept).label("✅ Accept").style(ButtonStyle::Success),
+                CreateButton::new(BetAction::ChallengeDecline).label("❌ Decline").style(ButtonStyle::Secondary),
+            ])]),
+            ChallengeStatus::Accepted => (BetAction::ChallengeVote as fn(u64) -> BetAction, vec![
+                CreateButton::new(BetAction::ChallengeArbitrate(0)).label("🚫 Refund both").style(ButtonStyle::Secondary)
+            ]),
+            ChallengeStatus::Disputed => (BetAction::ChallengeArbitrate as fn(u64) -> BetAction, vec![
+                CreateButton::new(BetAction::ChallengeArbitrate(0)).label("🚫 Refund both").style(ButtonStyle::Secondary)
+            ]),
+            _ => return Ok(vec![])
+        };
+        for user in [challenge.opponent, challenge.challenger] {
+            let name = http.get_user(user.into()).await?.name;
+            row.insert(0, CreateButton::new(vote(user)).label(format!("🏆 {} won", shorten(&name, 30))).style(ButtonStyle::Primary));
+        }
+        Ok(vec![CreateActionRow::Buttons(row)])
+    }
+
+    pub async fn challenge_accept_action(&self, ctx: Context, command: &ComponentInteraction, id: u64) -> Result<()> {
+        let mut challenge = self.db.challenge(id)?.ok_or(anyhow!("challenge {} not found", id))?;
+        let cfg = self.config(challenge.guild)?;
+        self.bets.create_account(challenge.guild, challenge.opponent, cfg.starting_coins as u64)?;
+        let error = if command.user.id.get() != challenge.opponent {
+            Some(format!("Only <@{}> can accept this challenge", challenge.opponent))
+        } else if challenge.status != ChallengeStatus::Pending || challenge.expires <= Utc::now().timestamp() {
+            Some("This challenge can't be accepted anymore".to_string())
+        } else if self.bets.balance(challenge.guild, challenge.opponent)? < challenge.amount {
+            Some(format!("You need {} {} available to accept", challenge.amount, cfg.currency))
+        } else if self.bets.balance(challenge.guild, challenge.challenger)? < challenge.amount {
+            Some(format!("<@{}> doesn't have {} {} available anymore", challenge.challenger, challenge.amount, cfg.currency))
+        } else if !self.db.transition_challenge(id, ChallengeStatus::Pending, ChallengeStatus::Accepted)? {
+            // it was accepted, declined or expired in the meantime
+            Some("This challenge can't be accepted anymore".to_string())
+        } else if let Err(why) = self.apply_diffs(challenge.guild, &[
+            (challenge.challenger, -(challenge.amount as i64)), (challenge.opponent, -(challenge.amount as i64))
+        ]) {
+            // both stakes are held by the bot until the challenge is settled, or neither is
+            self.db.transition_challenge(id, ChallengeStatus::Accepted, ChallengeStatus::Pending)?;
+            Some(format!("The stakes couldn't be put aside: {}", why))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(error).ephemeral(true))
+            ).await?;
+            bail!("can't accept challenge {}", id);
+        }
+        challenge.status = ChallengeStatus::Accepted;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(challenge_display(&challenge, &cfg.currency))
+                    .components(self.challenge_components(&ctx.http, &challenge).await?)
+            )
+        ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(challenge.guild)).await
+    }
+
+    /// The opponent declines the challenge, or the challenger calls it off
+    pub async fn challenge_decline_action(&self, ctx: Context, command: &ComponentInteraction, id: u64) -> Result<()> {
+        let mut challenge = self.db.challenge(id)?.ok_or(anyhow!("challenge {} not found", id))?;
+        let user = command.user.id.get();
+        if (user != challenge.opponent && user != challenge.challenger) 
+            || !self.db.transition_challenge(id, ChallengeStatus::Pending, ChallengeStatus::Declined)? {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().content("You can't decline this challenge").ephemeral(true)
+                )
+            ).await?;
+            bail!("can't decline challenge {}", id);
+        }
+        challenge.status = ChallengeStatus::Declined;
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(challenge_display(&challenge, &self.config(challenge.guild)?.currency))
+                    .components(vec![])
+            )
+        ).await?;
+        Ok(())
+    }
+
+    /// Settles the challenge once both sides agree on the winner, asks for an admin if they don't.
+    /// An admin picking a winner settles it right away, in case one side never votes
+    pub async fn challenge_vote_action(&self, ctx: Context, command: &ComponentInteraction, id: u64, winner: u64) -> Result<()> {
+        let challenge = self.db.challenge(id)?.ok_or(anyhow!("challenge {} not found", id))?;
+        let user = command.user.id.get();
+        if challenge.status == ChallengeStatus::Accepted && user != challenge.opponent && user != challenge.challenger 
+            && Self::is_admin(command.member.as_ref())? {
+            return self.settle_challenge(&ctx, command, challenge, Some(winner)).await;
+        }
+        if challenge.status != ChallengeStatus::Accepted || (user != challenge.opponent && user != challenge.challenger) {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only the 2 sides of the challenge can confirm its winner")
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("can't vote on challenge {}", id);
+        }
+        self.db.set_challenge_vote(id, user, winner)?;
+        let mut challenge = self.db.challenge(id)?.ok_or(anyhow!("challenge {} not found", id))?;
+        match (challenge.challenger_vote, challenge.opponent_vote) {
+            (Some(challenger_vote), Some(opponent_vote)) if challenger_vote == opponent_vote => 
+                return self.settle_challenge(&ctx, command, challenge, Some(challenger_vote)).await,
+            (Some(_), Some(_)) if self.db.transition_challenge(id, ChallengeStatus::Accepted, ChallengeStatus::Disputed)? => 
+                challenge.status = ChallengeStatus::Disputed,
+            _ => {}
+        }
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(challenge_display(&challenge, &self.config(challenge.guild)?.currency))
+                    .components(self.challenge_components(&ctx.http, &challenge).await?)
+            )
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn challenge_arbitrate_action(&self, ctx: Context, command: &ComponentInteraction, id: u64, winner: u64) -> Result<()> {
+        let challenge = self.db.challenge(id)?.ok_or(anyhow!("challenge {} not found", id))?;
+        let open = matches!(challenge.status, ChallengeStatus::Accepted | ChallengeStatus::Disputed);
+        if !open || !Self::is_admin(command.member.as_ref())? {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only admins can arbitrate a challenge in progress")
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!("can't arbitrate challenge {}", id);
+        }
+        self.settle_challenge(&ctx, command, challenge, if winner == 0 { None } else { Some(winner) }).await
+    }
+
+    /// Pays both stakes to the winner minus the rake on what they won, or gives them back if there's no winner
+    async fn settle_challenge(
+        &self, ctx: &Context, command: &ComponentInteraction, mut challenge: Challenge, winner: Option<u64>
+    ) -> Result<()> {
+        let cfg = self.config(challenge.guild)?;
+        let pool = 2*challenge.amount;
+        let rake = if winner.is_some() { challenge.amount*cfg.rake as u64/100 } else { 0 };
+        let (payout, transfers) = match winner {
+            Some(winner) => (pool - rake, vec![(winner, (pool - rake) as i64)]),
+            None => (0, vec![(challenge.challenger, challenge.amount as i64), (challenge.opponent, challenge.amount as i64)])
+        };
+        // the challenge is closed before the stakes are paid so a concurrent settlement can't pay them twice
+        let open = matches!(challenge.status, ChallengeStatus::Accepted | ChallengeStatus::Disputed);
+        if !open || !self.db.settle_challenge(challenge.id, challenge.status, winner, payout)? {
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().content("This challenge was already settled").ephemeral(true)
+                )
+            ).await?;
+            bail!("challenge {} was already settled", challenge.id);
+        }
+        let settled = if winner.is_some() { ChallengeStatus::Won } else { ChallengeStatus::Refunded };
+        if let Err(why) = self.apply_diffs(challenge.guild, &transfers) {
+            self.db.transition_challenge(challenge.id, settled, challenge.status)?;
+            command.create_response(&ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("The challenge couldn't be paid, it's still open\n-# {}", why))
+                        .ephemeral(true)
+                )
+            ).await?;
+            bail!(why);
+        }
+        self.db.add_to_pot(challenge.guild, rake as i64)?;
+        challenge.status = settled;
+        challenge.winner = winner;
+        challenge.payout = Some(payout);
+        command.create_response(&ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(challenge_display(&challenge, &cfg.currency))
+                    .components(vec![])
+            )
+        ).await?;
+        self.update_leaderboards(&ctx.http, GuildId::new(challenge.guild)).await
+    }
+
+    /// Closes a challenge that wasn't accepted in time, nothing was staked yet
+    pub async fn expire_challenge(&self, http: &Http, channel_id: ChannelId, id: u64) -> Result<()> {
+        let mut challenge = self.db.challenge(id)?.ok_or(anyhow!("challenge {} not found", id))?;
+        if !self.db.transition_challenge(id, ChallengeStatus::Pending, ChallengeStatus::Expired)? {
+            // it was accepted or declined just in time
+            return Ok(());
+        }
+        challenge.status = ChallengeStatus::Expired;
+        let mut message = http.get_message(channel_id, id.into()).await?;
+        message.edit(http, 
+            EditMessage::new()
+                .content(challenge_display(&challenge, &self.config(challenge.guild)?.currency))
+                .components(vec![])
+        ).await?;
+        Ok(())
+    }
+
+    /// Every position taken on the bet
+    fn wagers(&self, server: u64, bet_id: u64) -> Result<Vec<Wager>> {
+        let mut wagers = Vec::new();
+        for account in self.bets.accounts(server)? {
+            match self.bets.position(account.user, bet_id) {
+                Result::Ok(position) => wagers.push(Wager { 
+                    user: account.user, outcome: position.outcome, amount: position.amount 
+                }),
+                Err(betting::BetError::NotFound) => {},
+                Err(err) => bail!(err)
+            }
+        }
+        Ok(wagers)
+    }
+
+    pub async fn split_select_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.check_rights(&ctx, command, bet_id).await?;
+        let ComponentInteractionDataKind::StringSelect { values } = &command.data.kind else {
+            bail!("expected a string select");
+        };
+        let outcomes = self.outcome_descs(&ctx.http, command.channel_id, bet_id).await?;
+        let mut inputs = Vec::new();
+        for value in values {
+            let outcome = outcomes.get(value.parse::<usize>()?).ok_or(anyhow!("bet {} has no outcome {}", bet_id, value))?;
+            inputs.push(CreateActionRow::InputText(
+                CreateInputText::new(
+                    InputTextStyle::Short, 
+                    format!("Weight of: {}", shorten(outcome, 30)),
+                    value
+                ).value("1").required(true)
+            ));
+        }
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Modal(
+                CreateModal::new(BetAction::SplitOrder(bet_id), "Share of the pool of each winner")
+                    .components(inputs)
+            )
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn split_order_action(&self, ctx: Context, command: &ModalInteraction, bet_id: u64) -> Result<()> {
+        let _guard = self.guard_bet(bet_id).await;
+        let guild_id = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        if !self.has_rights(&command.user, command.member.as_ref(), bet_id)? {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only the bet author or admins can perform this action")
+                        .ephemeral(true)
+                    )
+            ).await?;
+            bail!("user is not bet author and not admin");
+        }
+        let mut weights = Vec::new();
+        for row in &command.data.components {
+            if let Some(ActionRowComponent::InputText(input)) = row.components.get(0) {
+                let weight = input.value.clone().unwrap_or_default().trim().parse::<u64>();
+                let Result::Ok(weight) = weight else {
+                    command.create_response(
+                        &ctx.http, 
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("Weights must be positive whole numbers")
+                                .ephemeral(true)
+                        )
+                    ).await?;
+                    bail!("invalid weight");
+                };
+                weights.push((input.custom_id.parse::<usize>()?, weight));
+            }
+        }
+        let cfg = self.config(guild_id.get())?;
+        let outcomes = self.outcome_descs(&ctx.http, command.channel_id, bet_id).await?;
+        let settlement = self.settle(guild_id.get(), bet_id, &weights)?;
+        let income_accounts = self.pay_income(guild_id.get(), cfg.income as u64)?;
+        let winners: Vec<String> = settlement.payouts.iter().map(|(outcome_id, payout)| format!(
+            "## > {}\n{} {} shared between its bettors", 
+            outcomes.get(*outcome_id).map(String::as_str).unwrap_or_default(), payout, cfg.currency
+        )).collect();
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "## Bet resolved with several winners\n{}{}\n-# everyone also wins {}`{}` for activity bonus !", 
+                        winners.join("\n"), rake_display(settlement.rake, &cfg.currency) + &parlays_display(&settlement.parlays, &cfg.currency), cfg.income, cfg.currency
+                    ))
+                    .components(Self::undo_components(bet_id, &cfg))
+            )
+        ).await?;
+        let announcement = command.get_response(&ctx.http).await?;
+        self.track_resolution(guild_id.get(), command.channel_id.get(), announcement.id.get(), bet_id, settlement, income_accounts)?;
+        self.close_bet(&ctx.http, command.channel_id, bet_id).await?;
+        self.update_leaderboards(&ctx.http, guild_id).await?;
+        Ok(())
+    }
+
+    pub async fn number_result_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.check_rights(&ctx, command, bet_id).await?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Modal(
+                CreateModal::new(BetAction::NumberOrder(bet_id), "Result of the bet")
+                    .components(vec![CreateActionRow::InputText(
+                        CreateInputText::new(InputTextStyle::Short, "Actual number", "result").required(true)
+                    )])
+            )
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn number_order_action(&self, ctx: Context, command: &ModalInteraction, bet_id: u64) -> Result<()> {
+        let _guard = self.guard_bet(bet_id).await;
+        let guild_id = command.guild_id.ok_or(anyhow!("action triggered outside server"))?;
+        if !self.has_rights(&command.user, command.member.as_ref(), bet_id)? {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only the bet author or admins can perform this action")
+                        .ephemeral(true)
+                    )
+            ).await?;
+            bail!("user is not bet author and not admin");
+        }
+        let result = command.data.components.iter()
+            .filter_map(|row| row.components.get(0))
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) => input.value.clone(),
+                _ => None
+            })
+            .and_then(|value| Self::parse_number(&value));
+        let Some(result) = result else {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().content("The result must be a number").ephemeral(true)
+                )
+            ).await?;
+            bail!("invalid result");
+        };
+        if let Some(payout) = self.db.guess_bet(bet_id)? {
+            return self.resolve_guesses(ctx, command, guild_id, bet_id, result, payout).await;
+        }
+        let (line, exact) = self.db.number_bet(bet_id)?.ok_or(anyhow!("bet {} is not an over/under bet", bet_id))?;
+        // outcomes are Over, Under, then Exactly if enabled
+        let winner = if result > line {
+            0
+        } else if result < line {
+            1
+        } else if exact {
+            2
+        } else {
+            // a push, nobody wins
+            self.refund_bet(guild_id.get(), bet_id)?;
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("*The result is exactly {}, participants have been refunded.*", line))
+                        .components(vec![])
+                )
+            ).await?;
+            self.delete_outcomes(&ctx.http, command.channel_id, bet_id).await?;
+            self.update_leaderboards(&ctx.http, guild_id).await?;
+            return Ok(());
+        };
+        let cfg = self.config(guild_id.get())?;
+        let content = self.outcome_content(&ctx.http, command.channel_id, guild_id.get(), BetOutcome { bet_id, outcome_id: winner }).await?;
+        let settlement = self.settle(guild_id.get(), bet_id, &[(winner, 1)])?;
+        let total: u64 = settlement.payouts.iter().map(|(_, payout)| payout).sum();
+        let income_accounts = self.pay_income(guild_id.get(), cfg.income as u64)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "## Bet resolved as\n{}\nThe result was **{}**, {} {} is shared between the winners.{}\n-# everyone also wins {}`{}` for activity bonus !", 
+                        content, result, total.saturating_sub(settlement.rake), cfg.currency, 
+                        rake_display(settlement.rake, &cfg.currency) + &parlays_display(&settlement.parlays, &cfg.currency), cfg.income, cfg.currency
+                    ))
+                    .components(Self::undo_components(bet_id, &cfg))
+            )
+        ).await?;
+        let announcement = command.get_response(&ctx.http).await?;
+        self.track_resolution(guild_id.get(), command.channel_id.get(), announcement.id.get(), bet_id, settlement, income_accounts)?;
+        self.close_bet(&ctx.http, command.channel_id, bet_id).await?;
+        self.update_leaderboards(&ctx.http, guild_id).await?;
+        Ok(())
+    }
+
+    async fn resolve_guesses(
+        &self, ctx: Context, command: &ModalInteraction, guild_id: GuildId, bet_id: u64, truth: f64, payout: GuessPayout
+    ) -> Result<()> {
+        let cfg = self.config(guild_id.get())?;
+        let guesses = self.db.guesses(bet_id)?;
+        let settlement = self.pay_guesses(guild_id.get(), bet_id, truth, payout)?;
+        let income_accounts = self.pay_income(guild_id.get(), cfg.income as u64)?;
+        let mut winners: Vec<(u64, i64)> = settlement.diffs.iter().filter(|(_, diff)| *diff > 0).cloned().collect();
+        winners.sort_by_key(|(_, diff)| -diff);
+        let winners = winners.iter().map(|(user, diff)| format!(
+            "<@{}> guessed {} → {} {}", user, guesses.get(user).copied().unwrap_or(f64::NAN), diff, cfg.currency
+        )).collect::<Vec<_>>().join("\n");
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "## Bet resolved\nThe answer was **{}** ({} payout)\n{}{}\n-# everyone also wins {}`{}` for activity bonus !", 
+                        truth, payout.as_str(), winners, rake_display(settlement.rake, &cfg.currency) + &parlays_display(&settlement.parlays, &cfg.currency), cfg.income, cfg.currency
+                    ))
+                    .components(Self::undo_components(bet_id, &cfg))
+            )
+        ).await?;
+        let announcement = command.get_response(&ctx.http).await?;
+        self.track_resolution(guild_id.get(), command.channel_id.get(), announcement.id.get(), bet_id, settlement, income_accounts)?;
+        self.close_bet(&ctx.http, command.channel_id, bet_id).await?;
+        self.update_leaderboards(&ctx.http, guild_id).await?;
+        Ok(())
+    }
+
+    /// Options to set the stake limits of a bet at its creation
+    fn stake_limit_options(command: CreateCommand) -> CreateCommand {
+        command
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Integer, 
+                "min_stake", 
+                "The smallest amount a user can bet, the server setting by default"
+            ).min_int_value(1).required(false))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Integer, 
+                "max_stake", 
+                "The biggest amount a user can bet, the server setting by default"
+            ).min_int_value(1).required(false))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Integer, 
+                "max_pool_share", 
+                "The biggest % of the pool a user can hold, the server setting by default"
+            ).min_int_value(1).max_int_value(100).required(false))
+    }
+
+    fn setting_option() -> CreateCommandOption {
+        SETTINGS.iter().fold(
+            CreateCommandOption::new(CommandOptionType::String, "setting", "The setting to change").required(true),
+            |option, setting| option.add_string_choice(*setting, *setting)
+        )
+    }
+
+    fn adjust_command_def(name: &str, description: &str) -> CreateCommand {
+        CreateCommand::new(name)
+            .description(description)
+            .default_member_permissions(Permissions::ADMINISTRATOR)
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::User, 
+                "user", 
+                "The user whose balance to change"
+            ).required(true))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Integer, 
+                "amount", 
+                "The amount of coins"
+            ).min_int_value(1).required(true))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String, 
+                "reason", 
+                "Why the balance is changed, for the audit log"
+            ).max_length(200).required(true))
+    }
+
+    pub async fn register_commands(&self, http: &Http, id: GuildId) {
+        println!("Registering slash commands for Guild {}", id);
+        if let Err(why) =
+            id.set_commands(http, vec![
+                CreateCommand::new("account").description("Check how much you have in your account."),
+                Self::stake_limit_options(CreateCommand::new("bet")
+                    .description("Create a bet, opens a form if the outcomes are left out.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "desc", 
+                        "The description of the bet"
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "options", 
+                        "The possible outcomes of the bet"
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "closes_in", 
+                        "Minutes after which the bet is locked automatically"
+                    ).min_int_value(1).required(false))),
+                Self::stake_limit_options(CreateCommand::new("bet-number")
+                    .description("Create an over/under bet on a number.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "desc", 
+                        "The description of the bet"
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Number, 
+                        "line", 
+                        "The number to bet over or under"
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Boolean, 
+                        "exact", 
+                        "To add an 'Exactly' outcome"
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "closes_in", 
+                        "Minutes after which the bet is locked automatically"
+                    ).min_int_value(1).required(false))),
+                Self::stake_limit_options(CreateCommand::new("bet-odds")
+                    .description("Create a bet with fixed odds, you pay the winners from your bankroll.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "desc", 
+                        "The description of the bet"
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "options", 
                         "The possible outcomes of the bet"
-                    ).required(true)),
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "odds", 
+                        "The odds of each outcome, stake included, like 1.5 2.8 4"
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "bankroll", 
+                        "The coins you put aside to pay the winners"
+                    ).min_int_value(1).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "closes_in", 
+                        "Minutes after which the bet is locked automatically"
+                    ).min_int_value(1).required(false))),
+                Self::stake_limit_options(CreateCommand::new("bet-guess")
+                    .description("Create a bet where the closest guesses of a number win.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "desc", 
+                        "The description of the bet"
+                    ).required(true))
+                    .add_option(GUESS_PAYOUTS.iter().fold(
+                        CreateCommandOption::new(
+                            CommandOptionType::String, 
+                            "payout", 
+                            "How the pool is shared, the server setting by default"
+                        ).required(false),
+                        |option, payout| option.add_string_choice(payout.as_str(), payout.as_str())
+                    ))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "closes_in", 
+                        "Minutes after which the bet is locked automatically"
+                    ).min_int_value(1).required(false))),
+                CreateCommand::new("history")
+                    .description("Lists the bets and transfers of a user.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Whose bets to list, yourself by default"
+                    ).required(false)),
+                CreateCommand::new("stats")
+                    .description("Shows betting stats of a user or of the server.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Whose stats to show, yourself by default"
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Boolean, 
+                        "server", 
+                        "To show the stats of the whole server instead"
+                    ).required(false)),
+                CreateCommand::new("config")
+                    .description("View or change the settings of the bot in this server.")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "view", 
+                        "Show the current settings"
+                    ))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "set", 
+                        "Change a setting"
+                    ).add_sub_option(Self::setting_option()).add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "value", 
+                        "The new value of the setting"
+                    ).required(true)))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "reset", 
+                        "Put a setting back to its default value"
+                    ).add_sub_option(Self::setting_option())),
+                Self::adjust_command_def("grant", "Give coins to a user."),
+                Self::adjust_command_def("take", "Take coins from a user."),
+                CreateCommand::new("daily").description("Claim your daily coins, more if you come every day."),
+                CreateCommand::new("rebuy").description("Start over with some coins when you've lost everything."),
+                CreateCommand::new("give")
+                    .description("Give some of your coins to another user.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Who to give the coins to"
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "amount", 
+                        "The amount of coins"
+                    ).min_int_value(1).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "note", 
+                        "A message for the recipient"
+                    ).required(false)),
+                CreateCommand::new("parlay")
+                    .description("Stake on one outcome of several open bets, you win if they all win.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "amount", 
+                        "The amount of coins"
+                    ).min_int_value(1).required(true)),
+                CreateCommand::new("challenge")
+                    .description("Challenge a user to a one-on-one bet.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Who to challenge"
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "amount", 
+                        "The coins staked by each side"
+                    ).min_int_value(1).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "claim", 
+                        "What you bet will happen"
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "expires_in", 
+                        "Minutes the user has to accept, 60 by default"
+                    ).min_int_value(1).required(false)),
+                CreateCommand::new("audit")
+                    .description("Lists the coins granted and taken by admins.")
+                    .default_member_permissions(Permissions::ADMINISTRATOR),
+                CreateCommand::new("pot")
+                    .description("The community pot, filled by the rake on bet winnings.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "view", 
+                        "Show how much is in the pot"
+                    ))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "lottery", 
+                        "(admin) Give the whole pot to a random user"
+                    ))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "top", 
+                        "(admin) Share the pot between the top of the leaderboard"
+                    ).add_sub_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "n", 
+                        "How many users to share it with"
+                    ).min_int_value(1).required(true)))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "bonus", 
+                        "(admin) Share the pot with everyone as an activity bonus"
+                    )),
+                CreateCommand::new("season")
+                    .description("Manage the betting seasons of this server.")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::SubCommand, 
+                        "end", 
+                        "Archive the leaderboard and reset every balance"
+                    )),
                 CreateCommand::new("leaderboard")    
                     .description("Displays the leadeboard.")
                     .add_option(CreateCommandOption::new(
@@ -331,6 +3064,11 @@ impl BettingBot {
                         "permanent", 
                         "To make a ever updating leaderboard").required(false)
                     )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "season", 
+                        "To show the final leaderboard of a past season"
+                    ).min_int_value(1).required(false))
             ]
         ).await
         {
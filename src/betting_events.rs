@@ -5,7 +5,7 @@ use serenity::{
         application::Interaction, gateway::Ready, guild::Guild, id::GuildId
     }, prelude::*
 };
-use crate::{betting_bot::BettingBot, serialize_utils::BetAction};
+use crate::{betting_bot::BettingBot, bot_db::StakeLimits, serialize_utils::BetAction};
 
 #[async_trait]
 impl EventHandler for BettingBot {
@@ -19,7 +19,23 @@ impl EventHandler for BettingBot {
                     if let Err(why) = match command_name.as_str() {
                         "account" => self.account_command(ctx, command).await,
                         "bet" => self.bet_command(ctx, command).await,
+                        "bet-number" => self.number_bet_command(ctx, command).await,
+                        "bet-guess" => self.guess_bet_command(ctx, command).await,
+                        "bet-odds" => self.odds_bet_command(ctx, command).await,
                         "leaderboard" => self.leaderboard_command(ctx, command).await,
+                        "history" => self.history_command(ctx, command).await,
+                        "stats" => self.stats_command(ctx, command).await,
+                        "config" => self.config_command(ctx, command).await,
+                        "grant" => self.adjust_command(ctx, command, 1).await,
+                        "take" => self.adjust_command(ctx, command, -1).await,
+                        "audit" => self.audit_command(ctx, command).await,
+                        "give" => self.give_command(ctx, command).await,
+                        "daily" => self.daily_command(ctx, command).await,
+                        "rebuy" => self.rebuy_command(ctx, command).await,
+                        "season" => self.season_command(ctx, command).await,
+                        "pot" => self.pot_command(ctx, command).await,
+                        "parlay" => self.parlay_command(ctx, command).await,
+                        "challenge" => self.challenge_command(ctx, command).await,
                         _ => Err(anyhow!("Unknown command")),
                     } {
                         warn!(target: "betting-bot", "\\{}: {}", command_name, why);
@@ -39,8 +55,27 @@ impl EventHandler for BettingBot {
             Interaction::Component(command) => if let Err(why) = match BetAction::try_from(command.data.custom_id.clone()) {
                 Ok(BetAction::Lock) => self.lock_action(ctx, &command, command.message.id.get()).await,
                 Ok(BetAction::Abort) => self.abort_action(ctx, &command, command.message.id.get()).await,
+                Ok(BetAction::Edit) => self.edit_action(ctx, &command, command.message.id.get()).await,
                 Ok(BetAction::BetClick(bet_outcome)) => self.bet_click_action(ctx, &command, bet_outcome).await,
+                Ok(BetAction::QuickBet(bet_outcome, percent)) => self.quick_bet_action(ctx, &command, bet_outcome, percent).await,
                 Ok(BetAction::Resolve(bet_outcome)) => self.resolve_action(ctx, &command, bet_outcome).await,
+                Ok(BetAction::SplitSelect(bet_id)) => self.split_select_action(ctx, &command, bet_id).await,
+                Ok(BetAction::Undo(bet_id)) => self.undo_action(ctx, &command, bet_id).await,
+                Ok(BetAction::History(user, page)) => self.history_action(ctx, &command, user, page).await,
+                Ok(BetAction::Audit(page)) => self.audit_action(ctx, &command, page).await,
+                Ok(BetAction::NumberResult(bet_id)) => self.number_result_action(ctx, &command, bet_id).await,
+                Ok(BetAction::Withdraw(bet_id)) => self.withdraw_action(ctx, &command, bet_id).await,
+                Ok(BetAction::Switch(bet_outcome)) => self.switch_action(ctx, &command, bet_outcome).await,
+                Ok(BetAction::PickOutcome(bet_id)) => self.pick_outcome_action(ctx, &command, bet_id).await,
+                Ok(BetAction::SwitchPick(bet_id)) => self.switch_pick_action(ctx, &command, bet_id).await,
+                Ok(BetAction::ResolvePick(bet_id)) => self.resolve_pick_action(ctx, &command, bet_id).await,
+                Ok(BetAction::ParlayOrder(amount)) => self.parlay_order_action(ctx, &command, amount).await,
+                Ok(BetAction::ChallengeAccept) => self.challenge_accept_action(ctx, &command, command.message.id.get()).await,
+                Ok(BetAction::ChallengeDecline) => self.challenge_decline_action(ctx, &command, command.message.id.get()).await,
+                Ok(BetAction::ChallengeVote(winner)) => 
+                    self.challenge_vote_action(ctx, &command, command.message.id.get(), winner).await,
+                Ok(BetAction::ChallengeArbitrate(winner)) => 
+                    self.challenge_arbitrate_action(ctx, &command, command.message.id.get(), winner).await,
                 Err(why) => Err(why),
                 other => Err(anyhow!("Unhandled BetAction variant {:?}", other))
             } {
@@ -48,6 +83,12 @@ impl EventHandler for BettingBot {
             },
             Interaction::Modal(command) => if let Err(why) = match BetAction::try_from(command.data.custom_id.clone()) {
                 Ok(BetAction::BetOrder) => self.bet_order_action(ctx, &command).await,
+                Ok(BetAction::EditOrder(bet_id)) => self.edit_order_action(ctx, &command, bet_id).await,
+                Ok(BetAction::BetForm(closes_in, min_stake, max_stake, max_pool_share)) => 
+                    self.bet_form_action(ctx, &command, closes_in, StakeLimits { min_stake, max_stake, max_pool_share }).await,
+                Ok(BetAction::SplitOrder(bet_id)) => self.split_order_action(ctx, &command, bet_id).await,
+                Ok(BetAction::NumberOrder(bet_id)) => self.number_order_action(ctx, &command, bet_id).await,
+                Ok(BetAction::WithdrawOrder(bet_id)) => self.withdraw_order_action(ctx, &command, bet_id).await,
                 Err(why) => Err(why),
                 other => Err(anyhow!("Unhandled BetAction variant {:?}", other))
             } {
@@ -57,8 +98,10 @@ impl EventHandler for BettingBot {
         }
     }
 
-    async fn ready(&self, _ctx: Context, ready: Ready) {
+    async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
+        // picks up the deadlines left over from before a restart
+        self.run_scheduler(&ctx.http).await;
     }
 
     async fn cache_ready(&self, _ctx: Context, _guilds: Vec<GuildId>) {
@@ -67,5 +110,9 @@ impl EventHandler for BettingBot {
 
     async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: Option<bool>) {
         self.register_commands(&ctx.http, guild.id).await;
+        // balances may have changed while we were offline
+        if let Err(why) = self.update_leaderboards(&ctx.http, guild.id).await {
+            warn!(target: "betting-bot", "Couldn't refresh leaderboards of Guild {}: {}", guild.id, why);
+        }
     }
 }
\ No newline at end of file
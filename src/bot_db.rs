@@ -0,0 +1,1359 @@
+use std::{collections::HashMap, sync::Mutex};
+use anyhow::{Result, anyhow, bail};
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::payouts::{GuessPayout, Wager};
+
+/// A resolution that can still be undone
+pub struct Resolution {
+    pub bet: u64,
+    pub guild: u64,
+    pub channel: u64,
+    /// the announcement holding the Undo button
+    pub message: u64,
+    pub expires: i64,
+    pub income: u64,
+    /// accounts that got the activity bonus
+    pub income_accounts: Vec<u64>,
+    pub diffs: Vec<(u64, i64)>,
+    /// positions on the bet before it was resolved
+    pub wagers: Vec<Wager>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BetStatus {
+    Open,
+    Locked,
+    Resolved,
+    Aborted,
+}
+
+impl BetStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BetStatus::Open => "open",
+            BetStatus::Locked => "locked",
+            BetStatus::Resolved => "resolved",
+            BetStatus::Aborted => "aborted",
+        }
+    }
+}
+
+impl TryFrom<&str> for BetStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "open" => BetStatus::Open,
+            "locked" => BetStatus::Locked,
+            "resolved" => BetStatus::Resolved,
+            "aborted" => BetStatus::Aborted,
+            _ => bail!("'{}' is not a bet status", value)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParlayStatus {
+    Open,
+    Won,
+    Lost,
+    /// one of the legs was aborted, the stake was refunded
+    Void,
+}
+
+impl ParlayStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParlayStatus::Open => "open",
+            ParlayStatus::Won => "won",
+            ParlayStatus::Lost => "lost",
+            ParlayStatus::Void => "void",
+        }
+    }
+}
+
+impl TryFrom<&str> for ParlayStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "open" => ParlayStatus::Open,
+            "won" => ParlayStatus::Won,
+            "lost" => ParlayStatus::Lost,
+            "void" => ParlayStatus::Void,
+            _ => bail!("'{}' is not a parlay status", value)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChallengeStatus {
+    /// waiting for the opponent to accept
+    Pending,
+    /// both stakes are in escrow, waiting for the 2 sides to confirm the winner
+    Accepted,
+    /// the 2 sides disagree on the winner, waiting for an admin
+    Disputed,
+    Won,
+    /// an admin gave both stakes back
+    Refunded,
+    Declined,
+    Expired,
+}
+
+impl ChallengeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChallengeStatus::Pending => "pending",
+            ChallengeStatus::Accepted => "accepted",
+            ChallengeStatus::Disputed => "disputed",
+            ChallengeStatus::Won => "won",
+            ChallengeStatus::Refunded => "refunded",
+            ChallengeStatus::Declined => "declined",
+            ChallengeStatus::Expired => "expired",
+        }
+    }
+}
+
+impl TryFrom<&str> for ChallengeStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "pending" => ChallengeStatus::Pending,
+            "accepted" => ChallengeStatus::Accepted,
+            "disputed" => ChallengeStatus::Disputed,
+            "won" => ChallengeStatus::Won,
+            "refunded" => ChallengeStatus::Refunded,
+            "declined" => ChallengeStatus::Declined,
+            "expired" => ChallengeStatus::Expired,
+            _ => bail!("'{}' is not a challenge status", value)
+        })
+    }
+}
+
+/// What shows up in /history
+pub enum HistoryEntry {
+    Position {
+        desc: String,
+        status: BetStatus,
+        outcome_desc: String,
+        stake: u64,
+        /// None until the bet is settled
+        payout: Option<u64>,
+        placed: i64,
+    },
+    Transfer {
+        sender: u64,
+        recipient: u64,
+        amount: u64,
+        note: String,
+        at: i64,
+    },
+}
+
+/// The author of a fixed odds bet and the bankroll they put aside to pay the winners
+pub struct Bookmaker {
+    pub author: u64,
+    pub bankroll: u64,
+    /// decimal odds of each outcome, stake included
+    pub odds: Vec<f64>,
+}
+
+/// A single stake on several outcomes of different bets, it only pays if they all win
+pub struct Parlay {
+    pub id: u64,
+    pub user: u64,
+    pub amount: u64,
+    pub status: ParlayStatus,
+    /// what the user received if it won
+    pub payout: Option<u64>,
+    pub legs: Vec<ParlayLeg>,
+}
+
+/// A leg of a parlay, on one outcome of a bet
+pub struct ParlayLeg {
+    /// odds of the outcome when its bet was locked, stake included
+    pub odds: Option<f64>,
+    /// share of the pool that went to the outcome, None until its bet is resolved, 0 if it lost
+    pub share: Option<f64>,
+}
+
+/// A head-to-head bet between 2 users, identified by its message
+pub struct Challenge {
+    pub id: u64,
+    pub guild: u64,
+    pub channel: u64,
+    pub challenger: u64,
+    pub opponent: u64,
+    /// staked by each side
+    pub amount: u64,
+    pub claim: String,
+    pub status: ChallengeStatus,
+    /// until when the opponent can accept
+    pub expires: i64,
+    /// who each side says won
+    pub challenger_vote: Option<u64>,
+    pub opponent_vote: Option<u64>,
+    pub winner: Option<u64>,
+    pub payout: Option<u64>,
+}
+
+/// Stake limits set on a bet, overriding the ones of the guild
+#[derive(Debug, Default)]
+pub struct StakeLimits {
+    pub min_stake: Option<u64>,
+    pub max_stake: Option<u64>,
+    pub max_pool_share: Option<u32>,
+}
+
+/// The final position of a user in an archived season
+pub struct Standing {
+    pub user: u64,
+    pub balance: u64,
+    pub in_bet: u64,
+}
+
+/// A manual balance adjustment made by an admin
+pub struct AuditEntry {
+    pub admin: u64,
+    pub user: u64,
+    pub delta: i64,
+    pub reason: String,
+    pub at: i64,
+}
+
+/// Storage for the bot-side state that the betting crate doesn't track
+pub struct BotDB {
+    conn: Mutex<Connection>,
+}
+
+impl BotDB {
+    pub fn new(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS leaderboards (
+                guild INTEGER NOT NULL,
+                channel INTEGER NOT NULL,
+                message INTEGER NOT NULL,
+                PRIMARY KEY (guild, channel)
+            );
+            CREATE TABLE IF NOT EXISTS deadlines (
+                bet INTEGER PRIMARY KEY,
+                channel INTEGER NOT NULL,
+                deadline INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS resolutions (
+                bet INTEGER PRIMARY KEY,
+                guild INTEGER NOT NULL,
+                channel INTEGER NOT NULL,
+                message INTEGER NOT NULL,
+                expires INTEGER NOT NULL,
+                income INTEGER NOT NULL,
+                diffs TEXT NOT NULL,
+                wagers TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bet_winners (
+                bet INTEGER NOT NULL,
+                outcome INTEGER NOT NULL,
+                payout INTEGER NOT NULL,
+                PRIMARY KEY (bet, outcome)
+            );
+            CREATE TABLE IF NOT EXISTS resolution_incomes (
+                bet INTEGER NOT NULL,
+                user INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS escrows (
+                bet INTEGER PRIMARY KEY,
+                wagers TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bet_records (
+                bet INTEGER PRIMARY KEY,
+                guild INTEGER NOT NULL,
+                author INTEGER NOT NULL,
+                desc TEXT NOT NULL,
+                status TEXT NOT NULL,
+                created INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS positions (
+                bet INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                outcome INTEGER NOT NULL,
+                outcome_desc TEXT NOT NULL,
+                stake INTEGER NOT NULL,
+                payout INTEGER,
+                placed INTEGER NOT NULL,
+                PRIMARY KEY (bet, user)
+            );
+            CREATE TABLE IF NOT EXISTS guild_settings (
+                guild INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (guild, key)
+            );
+            CREATE TABLE IF NOT EXISTS audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                guild INTEGER NOT NULL,
+                admin INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                delta INTEGER NOT NULL,
+                reason TEXT NOT NULL,
+                at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS transfers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                guild INTEGER NOT NULL,
+                sender INTEGER NOT NULL,
+                recipient INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                note TEXT NOT NULL,
+                at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS dailies (
+                guild INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                last_claim INTEGER NOT NULL,
+                streak INTEGER NOT NULL,
+                PRIMARY KEY (guild, user)
+            );
+            CREATE TABLE IF NOT EXISTS bankruptcies (
+                guild INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                count INTEGER NOT NULL,
+                last_rebuy INTEGER NOT NULL,
+                PRIMARY KEY (guild, user)
+            );
+            CREATE TABLE IF NOT EXISTS seasons (
+                guild INTEGER NOT NULL,
+                season INTEGER NOT NULL,
+                ended INTEGER NOT NULL,
+                PRIMARY KEY (guild, season)
+            );
+            CREATE TABLE IF NOT EXISTS season_standings (
+                guild INTEGER NOT NULL,
+                season INTEGER NOT NULL,
+                rank INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                balance INTEGER NOT NULL,
+                in_bet INTEGER NOT NULL,
+                PRIMARY KEY (guild, season, user)
+            );
+            CREATE TABLE IF NOT EXISTS number_bets (
+                bet INTEGER PRIMARY KEY,
+                line REAL NOT NULL,
+                exact INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS guess_bets (
+                bet INTEGER PRIMARY KEY,
+                payout TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS guesses (
+                bet INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                guess REAL NOT NULL,
+                PRIMARY KEY (bet, user)
+            );
+            CREATE TABLE IF NOT EXISTS bookmakers (
+                bet INTEGER PRIMARY KEY,
+                author INTEGER NOT NULL,
+                bankroll INTEGER NOT NULL,
+                odds TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS pots (
+                guild INTEGER PRIMARY KEY,
+                amount INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS rakes (
+                bet INTEGER PRIMARY KEY,
+                guild INTEGER NOT NULL,
+                amount INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS stake_limits (
+                bet INTEGER PRIMARY KEY,
+                min_stake INTEGER,
+                max_stake INTEGER,
+                max_pool_share INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS bet_outcomes (
+                bet INTEGER NOT NULL,
+                outcome INTEGER NOT NULL,
+                desc TEXT NOT NULL,
+                PRIMARY KEY (bet, outcome)
+            );
+            CREATE TABLE IF NOT EXISTS parlays (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                guild INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                payout INTEGER,
+                placed INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS parlay_legs (
+                parlay INTEGER NOT NULL,
+                bet INTEGER NOT NULL,
+                outcome INTEGER NOT NULL,
+                odds REAL,
+                share REAL,
+                PRIMARY KEY (parlay, bet)
+            );
+            CREATE TABLE IF NOT EXISTS bet_threads (
+                bet INTEGER PRIMARY KEY,
+                channel INTEGER NOT NULL,
+                thread INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS compact_bets (
+                bet INTEGER PRIMARY KEY
+            );
+            CREATE TABLE IF NOT EXISTS challenges (
+                id INTEGER PRIMARY KEY,
+                guild INTEGER NOT NULL,
+                channel INTEGER NOT NULL,
+                challenger INTEGER NOT NULL,
+                opponent INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                claim TEXT NOT NULL,
+                status TEXT NOT NULL,
+                expires INTEGER NOT NULL,
+                challenger_vote INTEGER,
+                opponent_vote INTEGER,
+                winner INTEGER,
+                payout INTEGER
+            );"
+        )?;
+        Ok(BotDB { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn.lock().map_err(|_| anyhow!("bot database mutex poisoned"))
+    }
+
+    pub fn set_leaderboard(&self, guild: u64, channel: u64, message: u64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO leaderboards (guild, channel, message) VALUES (?1, ?2, ?3)",
+            params![guild, channel, message]
+        )?;
+        Ok(())
+    }
+
+    /// (channel, message) of every permanent leaderboard in the guild
+    pub fn leaderboards(&self, guild: u64) -> Result<Vec<(u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT channel, message FROM leaderboards WHERE guild = ?1")?;
+        let rows = stmt.query_map(params![guild], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn remove_leaderboard(&self, guild: u64, channel: u64) -> Result<()> {
+        self.conn()?.execute(
+            "DELETE FROM leaderboards WHERE guild = ?1 AND channel = ?2",
+            params![guild, channel]
+        )?;
+        Ok(())
+    }
+
+    pub fn set_deadline(&self, bet: u64, channel: u64, deadline: i64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO deadlines (bet, channel, deadline) VALUES (?1, ?2, ?3)",
+            params![bet, channel, deadline]
+        )?;
+        Ok(())
+    }
+
+    /// (bet, channel) of every bet whose deadline is before `now` (unix timestamp)
+    pub fn due_deadlines(&self, now: i64) -> Result<Vec<(u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT bet, channel FROM deadlines WHERE deadline <= ?1")?;
+        let rows = stmt.query_map(params![now], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn deadline(&self, bet: u64) -> Result<Option<i64>> {
+        Ok(self.conn()?.query_row(
+            "SELECT deadline FROM deadlines WHERE bet = ?1", params![bet], |row| row.get(0)
+        ).optional()?)
+    }
+
+    pub fn remove_deadline(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("DELETE FROM deadlines WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    pub fn add_resolution(&self, resolution: &Resolution) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO resolutions (bet, guild, channel, message, expires, income, diffs, wagers) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                resolution.bet, resolution.guild, resolution.channel, resolution.message, resolution.expires,
+                resolution.income, serde_json::to_string(&resolution.diffs)?, serde_json::to_string(&resolution.wagers)?
+            ]
+        )?;
+        tx.execute("DELETE FROM resolution_incomes WHERE bet = ?1", params![resolution.bet])?;
+        for user in &resolution.income_accounts {
+            tx.execute("INSERT INTO resolution_incomes (bet, user) VALUES (?1, ?2)", params![resolution.bet, user])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn resolution(&self, bet: u64) -> Result<Option<Resolution>> {
+        let conn = self.conn()?;
+        let row = conn.query_row(
+            "SELECT guild, channel, message, expires, income, diffs, wagers FROM resolutions WHERE bet = ?1",
+            params![bet],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get::<_, String>(5)?, row.get::<_, String>(6)?))
+        ).optional()?;
+        let mut stmt = conn.prepare("SELECT user FROM resolution_incomes WHERE bet = ?1")?;
+        let income_accounts = stmt.query_map(params![bet], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(match row {
+            Some((guild, channel, message, expires, income, diffs, wagers)) => Some(Resolution {
+                bet, guild, channel, message, expires, income, income_accounts,
+                diffs: serde_json::from_str(&diffs)?,
+                wagers: serde_json::from_str(&wagers)?,
+            }),
+            None => None
+        })
+    }
+
+    /// (bet, channel, message) of every resolution that can't be undone anymore
+    pub fn expired_resolutions(&self, now: i64) -> Result<Vec<(u64, u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT bet, channel, message FROM resolutions WHERE expires <= ?1")?;
+        let rows = stmt.query_map(params![now], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn remove_resolution(&self, bet: u64) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM resolutions WHERE bet = ?1", params![bet])?;
+        tx.execute("DELETE FROM resolution_incomes WHERE bet = ?1", params![bet])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Positions of an undone bet, held by the bot until it is resolved again
+    pub fn set_escrow(&self, bet: u64, wagers: &Vec<Wager>) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO escrows (bet, wagers) VALUES (?1, ?2)",
+            params![bet, serde_json::to_string(wagers)?]
+        )?;
+        Ok(())
+    }
+
+    pub fn escrow(&self, bet: u64) -> Result<Option<Vec<Wager>>> {
+        let conn = self.conn()?;
+        let wagers: Option<String> = conn.query_row(
+            "SELECT wagers FROM escrows WHERE bet = ?1", params![bet], |row| row.get(0)
+        ).optional()?;
+        Ok(match wagers {
+            Some(wagers) => Some(serde_json::from_str(&wagers)?),
+            None => None
+        })
+    }
+
+    pub fn remove_escrow(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("DELETE FROM escrows WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    /// Coins of the user held by the bot outside of the betting crate: 
+    /// bankrolls of unsettled fixed odds bets, accepted challenges, open parlays and undone positions
+    pub fn escrowed(&self, guild: u64, user: u64) -> Result<u64> {
+        let conn = self.conn()?;
+        let held: u64 = conn.query_row(
+            "SELECT
+                (SELECT COALESCE(SUM(k.bankroll), 0) FROM bookmakers k JOIN bet_records b ON k.bet = b.bet
+                    WHERE b.guild = ?1 AND k.author = ?2 AND b.status IN (?3, ?4))
+                + (SELECT COALESCE(SUM(amount), 0) FROM challenges 
+                    WHERE guild = ?1 AND (challenger = ?2 OR opponent = ?2) AND status IN (?5, ?6))
+                + (SELECT COALESCE(SUM(amount), 0) FROM parlays WHERE guild = ?1 AND user = ?2 AND status = ?7)",
+            params![
+                guild, user, BetStatus::Open.as_str(), BetStatus::Locked.as_str(),
+                ChallengeStatus::Accepted.as_str(), ChallengeStatus::Disputed.as_str(), ParlayStatus::Open.as_str()
+            ],
+            |row| row.get(0)
+        )?;
+        let mut stmt = conn.prepare("SELECT e.wagers FROM escrows e JOIN bet_records b ON e.bet = b.bet WHERE b.guild = ?1")?;
+        let escrows = stmt.query_map(params![guild], |row| row.get::<_, String>(0))?.collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut undone = 0;
+        for wagers in escrows {
+            let wagers: Vec<Wager> = serde_json::from_str(&wagers)?;
+            undone += wagers.iter().filter(|wager| wager.user == user).map(|wager| wager.amount).sum::<u64>();
+        }
+        Ok(held + undone)
+    }
+
+    pub fn add_bet_record(&self, bet: u64, guild: u64, author: u64, desc: &str, created: i64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO bet_records (bet, guild, author, desc, status, created) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![bet, guild, author, desc, BetStatus::Open.as_str(), created]
+        )?;
+        Ok(())
+    }
+
+    pub fn set_bet_thread(&self, bet: u64, channel: u64, thread: u64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO bet_threads (bet, channel, thread) VALUES (?1, ?2, ?3)", params![bet, channel, thread]
+        )?;
+        Ok(())
+    }
+
+    /// (channel of the bet message, thread of its outcomes) if the bet was posted with a thread
+    pub fn bet_thread(&self, bet: u64) -> Result<Option<(u64, u64)>> {
+        Ok(self.conn()?.query_row(
+            "SELECT channel, thread FROM bet_threads WHERE bet = ?1", params![bet], |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    pub fn set_compact_bet(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("INSERT OR IGNORE INTO compact_bets (bet) VALUES (?1)", params![bet])?;
+        Ok(())
+    }
+
+    /// Whether the bet and its outcomes are rendered in a single message
+    pub fn is_compact_bet(&self, bet: u64) -> Result<bool> {
+        Ok(self.conn()?.query_row(
+            "SELECT 1 FROM compact_bets WHERE bet = ?1", params![bet], |_| Ok(())
+        ).optional()?.is_some())
+    }
+
+    pub fn set_bet_desc(&self, bet: u64, desc: &str) -> Result<()> {
+        self.conn()?.execute("UPDATE bet_records SET desc = ?2 WHERE bet = ?1", params![bet, desc])?;
+        Ok(())
+    }
+
+    pub fn set_bet_status(&self, bet: u64, status: BetStatus) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE bet_records SET status = ?2 WHERE bet = ?1", params![bet, status.as_str()]
+        )?;
+        Ok(())
+    }
+
+    /// Adds the amount to the position of the user on the bet
+    pub fn record_wager(&self, bet: u64, user: u64, outcome: usize, outcome_desc: &str, amount: u64, placed: i64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO positions (bet, user, outcome, outcome_desc, stake, placed) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (bet, user) DO UPDATE SET stake = stake + excluded.stake, outcome_desc = excluded.outcome_desc",
+            params![bet, user, outcome, outcome_desc, amount, placed]
+        )?;
+        Ok(())
+    }
+
+    /// Sets what every participant received, the ones missing from `payouts` received nothing, 
+    /// and what was paid to each winning outcome
+    pub fn set_payouts(&self, bet: u64, payouts: &[(u64, u64)], winners: &[(usize, u64)]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE positions SET payout = 0 WHERE bet = ?1", params![bet])?;
+        for (user, payout) in payouts {
+            tx.execute("UPDATE positions SET payout = ?3 WHERE bet = ?1 AND user = ?2", params![bet, user, payout])?;
+        }
+        tx.execute("DELETE FROM bet_winners WHERE bet = ?1", params![bet])?;
+        for (outcome, payout) in winners {
+            tx.execute("INSERT INTO bet_winners (bet, outcome, payout) VALUES (?1, ?2, ?3)", params![bet, outcome, payout])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn refund_positions(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("UPDATE positions SET payout = stake WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    pub fn clear_payouts(&self, bet: u64) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE positions SET payout = NULL WHERE bet = ?1", params![bet])?;
+        tx.execute("DELETE FROM bet_winners WHERE bet = ?1", params![bet])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Positions and transfers of the user in the guild, most recent first
+    pub fn history(&self, guild: u64, user: u64, offset: usize, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT 0, b.desc, b.status, p.outcome_desc, p.stake, p.payout, p.placed AS at, 0, 0
+            FROM positions p JOIN bet_records b ON p.bet = b.bet
+            WHERE b.guild = ?1 AND p.user = ?2
+            UNION ALL
+            SELECT 1, note, '', '', amount, NULL, at, sender, recipient
+            FROM transfers WHERE guild = ?1 AND (sender = ?2 OR recipient = ?2)
+            ORDER BY at DESC LIMIT ?3 OFFSET ?4"
+        )?;
+        let rows = stmt.query_map(params![guild, user, limit, offset], |row| Ok((
+            row.get::<_, u8>(0)?, row.get(1)?, row.get::<_, String>(2)?, row.get(3)?, 
+            row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?, row.get(8)?
+        )))?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (kind, desc, status, outcome_desc, amount, payout, at, sender, recipient) = row?;
+            entries.push(if kind == 0 {
+                HistoryEntry::Position { 
+                    desc, status: BetStatus::try_from(status.as_str())?, outcome_desc, stake: amount, payout, placed: at 
+                }
+            } else {
+                HistoryEntry::Transfer { sender, recipient, amount, note: desc, at }
+            });
+        }
+        Ok(entries)
+    }
+
+    pub fn history_len(&self, guild: u64, user: u64) -> Result<usize> {
+        let transfers: usize = self.conn()?.query_row(
+            "SELECT COUNT(*) FROM transfers WHERE guild = ?1 AND (sender = ?2 OR recipient = ?2)",
+            params![guild, user],
+            |row| row.get(0)
+        )?;
+        Ok(self.positions_len(guild, user)? + transfers)
+    }
+
+    pub fn positions_len(&self, guild: u64, user: u64) -> Result<usize> {
+        Ok(self.conn()?.query_row(
+            "SELECT COUNT(*) FROM positions p JOIN bet_records b ON p.bet = b.bet WHERE b.guild = ?1 AND p.user = ?2",
+            params![guild, user],
+            |row| row.get(0)
+        )?)
+    }
+
+    /// (stake, payout) of the user's positions on resolved bets, oldest first
+    pub fn settled_positions(&self, guild: u64, user: u64) -> Result<Vec<(u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT p.stake, p.payout FROM positions p JOIN bet_records b ON p.bet = b.bet
+            WHERE b.guild = ?1 AND p.user = ?2 AND b.status = ?3 AND p.payout IS NOT NULL
+            ORDER BY p.placed"
+        )?;
+        let rows = stmt.query_map(
+            params![guild, user, BetStatus::Resolved.as_str()], |row| Ok((row.get(0)?, row.get(1)?))
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The author of the bets the user took part in the most, with the number of those bets
+    pub fn favourite_author(&self, guild: u64, user: u64) -> Result<Option<(u64, u64)>> {
+        Ok(self.conn()?.query_row(
+            "SELECT b.author, COUNT(*) AS n FROM positions p JOIN bet_records b ON p.bet = b.bet
+            WHERE b.guild = ?1 AND p.user = ?2 AND b.author != ?2
+            GROUP BY b.author ORDER BY n DESC LIMIT 1",
+            params![guild, user],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    /// (number of bets, number of bettors, total amount bet) in the guild, leaving out aborted bets
+    pub fn guild_volume(&self, guild: u64) -> Result<(u64, u64, u64)> {
+        Ok(self.conn()?.query_row(
+            "SELECT 
+                (SELECT COUNT(*) FROM bet_records WHERE guild = ?1 AND status != ?2),
+                COUNT(DISTINCT p.user), 
+                COALESCE(SUM(p.stake), 0) 
+            FROM positions p JOIN bet_records b ON p.bet = b.bet WHERE b.guild = ?1 AND b.status != ?2",
+            params![guild, BetStatus::Aborted.as_str()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        )?)
+    }
+
+    /// (description, number of bettors, amount bet) of the non aborted bets with the most bettors
+    pub fn popular_bets(&self, guild: u64, limit: usize) -> Result<Vec<(String, u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT b.desc, COUNT(*) AS n, SUM(p.stake) AS volume FROM positions p JOIN bet_records b ON p.bet = b.bet
+            WHERE b.guild = ?1 AND b.status != ?3 GROUP BY b.bet ORDER BY n DESC, volume DESC LIMIT ?2"
+        )?;
+        let rows = stmt.query_map(params![guild, limit, BetStatus::Aborted.as_str()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// (key, value) of the settings overriden in the guild
+    pub fn settings(&self, guild: u64) -> Result<Vec<(String, String)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT key, value FROM guild_settings WHERE guild = ?1")?;
+        let rows = stmt.query_map(params![guild], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn set_setting(&self, guild: u64, key: &str, value: &str) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO guild_settings (guild, key, value) VALUES (?1, ?2, ?3)",
+            params![guild, key, value]
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_setting(&self, guild: u64, key: &str) -> Result<()> {
+        self.conn()?.execute(
+            "DELETE FROM guild_settings WHERE guild = ?1 AND key = ?2", params![guild, key]
+        )?;
+        Ok(())
+    }
+
+    pub fn add_audit(&self, guild: u64, entry: &AuditEntry) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO audit (guild, admin, user, delta, reason, at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![guild, entry.admin, entry.user, entry.delta, entry.reason, entry.at]
+        )?;
+        Ok(())
+    }
+
+    /// Manual adjustments made in the guild, most recent first
+    pub fn audit(&self, guild: u64, offset: usize, limit: usize) -> Result<Vec<AuditEntry>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT admin, user, delta, reason, at FROM audit WHERE guild = ?1 ORDER BY id DESC LIMIT ?2 OFFSET ?3"
+        )?;
+        let rows = stmt.query_map(params![guild, limit, offset], |row| Ok(AuditEntry {
+            admin: row.get(0)?, user: row.get(1)?, delta: row.get(2)?, reason: row.get(3)?, at: row.get(4)?
+        }))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn audit_len(&self, guild: u64) -> Result<usize> {
+        Ok(self.conn()?.query_row(
+            "SELECT COUNT(*) FROM audit WHERE guild = ?1", params![guild], |row| row.get(0)
+        )?)
+    }
+
+    pub fn add_transfer(&self, guild: u64, sender: u64, recipient: u64, amount: u64, note: &str, at: i64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO transfers (guild, sender, recipient, amount, note, at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![guild, sender, recipient, amount, note, at]
+        )?;
+        Ok(())
+    }
+
+    /// (last claim timestamp, streak) of the user's /daily
+    pub fn daily(&self, guild: u64, user: u64) -> Result<Option<(i64, u32)>> {
+        Ok(self.conn()?.query_row(
+            "SELECT last_claim, streak FROM dailies WHERE guild = ?1 AND user = ?2",
+            params![guild, user],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    /// Records the claim unless the user already claimed since `day_start`, returns false if they did
+    pub fn claim_daily(&self, guild: u64, user: u64, last_claim: i64, streak: u32, day_start: i64) -> Result<bool> {
+        Ok(self.conn()?.execute(
+            "INSERT INTO dailies (guild, user, last_claim, streak) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (guild, user) DO UPDATE SET last_claim = ?3, streak = ?4 WHERE last_claim < ?5",
+            params![guild, user, last_claim, streak, day_start]
+        )? == 1)
+    }
+
+    /// (bankruptcy count, last rebuy timestamp) of the user
+    pub fn bankruptcy(&self, guild: u64, user: u64) -> Result<Option<(u32, i64)>> {
+        Ok(self.conn()?.query_row(
+            "SELECT count, last_rebuy FROM bankruptcies WHERE guild = ?1 AND user = ?2",
+            params![guild, user],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    /// Records the rebuy unless the user already rebought since `cooldown_start`, returns false if they did
+    pub fn add_bankruptcy(&self, guild: u64, user: u64, at: i64, cooldown_start: i64) -> Result<bool> {
+        Ok(self.conn()?.execute(
+            "INSERT INTO bankruptcies (guild, user, count, last_rebuy) VALUES (?1, ?2, 1, ?3)
+            ON CONFLICT (guild, user) DO UPDATE SET count = count + 1, last_rebuy = excluded.last_rebuy
+            WHERE last_rebuy < ?4",
+            params![guild, user, at, cooldown_start]
+        )? == 1)
+    }
+
+    /// Bankruptcy count of every user of the guild that went bankrupt
+    pub fn bankruptcies(&self, guild: u64) -> Result<HashMap<u64, u32>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT user, count FROM bankruptcies WHERE guild = ?1")?;
+        let rows = stmt.query_map(params![guild], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Number of the season in progress, seasons start at 1
+    pub fn current_season(&self, guild: u64) -> Result<u32> {
+        Ok(self.conn()?.query_row(
+            "SELECT COALESCE(MAX(season), 0) + 1 FROM seasons WHERE guild = ?1",
+            params![guild],
+            |row| row.get(0)
+        )?)
+    }
+
+    /// Archives the current season with its standings, best first
+    pub fn archive_season(&self, guild: u64, season: u32, standings: &[Standing], ended: i64) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("INSERT INTO seasons (guild, season, ended) VALUES (?1, ?2, ?3)", params![guild, season, ended])?;
+        for (rank, standing) in standings.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO season_standings (guild, season, rank, user, balance, in_bet) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![guild, season, rank+1, standing.user, standing.balance, standing.in_bet]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// End timestamp and standings of an archived season, best first
+    pub fn season(&self, guild: u64, season: u32) -> Result<Option<(i64, Vec<Standing>)>> {
+        let conn = self.conn()?;
+        let Some(ended) = conn.query_row(
+            "SELECT ended FROM seasons WHERE guild = ?1 AND season = ?2",
+            params![guild, season],
+            |row| row.get(0)
+        ).optional()? else {
+            return Ok(None);
+        };
+        let mut stmt = conn.prepare(
+            "SELECT user, balance, in_bet FROM season_standings WHERE guild = ?1 AND season = ?2 ORDER BY rank"
+        )?;
+        let rows = stmt.query_map(params![guild, season], |row| Ok(Standing {
+            user: row.get(0)?, balance: row.get(1)?, in_bet: row.get(2)?
+        }))?;
+        Ok(Some((ended, rows.collect::<rusqlite::Result<_>>()?)))
+    }
+
+    /// (rank, season) of the best placement of the user in the archived seasons
+    pub fn best_placement(&self, guild: u64, user: u64) -> Result<Option<(u32, u32)>> {
+        Ok(self.conn()?.query_row(
+            "SELECT rank, season FROM season_standings WHERE guild = ?1 AND user = ?2 ORDER BY rank, season LIMIT 1",
+            params![guild, user],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    pub fn add_number_bet(&self, bet: u64, line: f64, exact: bool) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO number_bets (bet, line, exact) VALUES (?1, ?2, ?3)",
+            params![bet, line, exact]
+        )?;
+        Ok(())
+    }
+
+    /// (line, has an Exactly outcome) if the bet is an over/under bet
+    pub fn number_bet(&self, bet: u64) -> Result<Option<(f64, bool)>> {
+        Ok(self.conn()?.query_row(
+            "SELECT line, exact FROM number_bets WHERE bet = ?1",
+            params![bet],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    pub fn add_guess_bet(&self, bet: u64, payout: GuessPayout) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO guess_bets (bet, payout) VALUES (?1, ?2)",
+            params![bet, payout.as_str()]
+        )?;
+        Ok(())
+    }
+
+    /// Payout of the bet if it's a closest guess bet
+    pub fn guess_bet(&self, bet: u64) -> Result<Option<GuessPayout>> {
+        let payout: Option<String> = self.conn()?.query_row(
+            "SELECT payout FROM guess_bets WHERE bet = ?1",
+            params![bet],
+            |row| row.get(0)
+        ).optional()?;
+        payout.map(|payout| GuessPayout::try_from(payout.as_str())).transpose()
+    }
+
+    pub fn set_guess(&self, bet: u64, user: u64, guess: f64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO guesses (bet, user, guess) VALUES (?1, ?2, ?3)",
+            params![bet, user, guess]
+        )?;
+        Ok(())
+    }
+
+    pub fn guesses(&self, bet: u64) -> Result<HashMap<u64, f64>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT user, guess FROM guesses WHERE bet = ?1")?;
+        let rows = stmt.query_map(params![bet], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn add_bookmaker(&self, bet: u64, bookmaker: &Bookmaker) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO bookmakers (bet, author, bankroll, odds) VALUES (?1, ?2, ?3, ?4)",
+            params![bet, bookmaker.author, bookmaker.bankroll, serde_json::to_string(&bookmaker.odds)?]
+        )?;
+        Ok(())
+    }
+
+    /// The bookmaker of the bet if it has fixed odds
+    pub fn bookmaker(&self, bet: u64) -> Result<Option<Bookmaker>> {
+        let row: Option<(u64, u64, String)> = self.conn()?.query_row(
+            "SELECT author, bankroll, odds FROM bookmakers WHERE bet = ?1",
+            params![bet],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        ).optional()?;
+        row.map(|(author, bankroll, odds)| Ok(Bookmaker { author, bankroll, odds: serde_json::from_str(&odds)? })).transpose()
+    }
+
+    /// Coins in the community pot of the guild
+    pub fn pot(&self, guild: u64) -> Result<u64> {
+        Ok(self.conn()?.query_row(
+            "SELECT amount FROM pots WHERE guild = ?1",
+            params![guild],
+            |row| row.get(0)
+        ).optional()?.unwrap_or(0))
+    }
+
+    /// Adds to the community pot, or removes from it if negative, fails if the pot doesn't hold enough to remove
+    pub fn add_to_pot(&self, guild: u64, delta: i64) -> Result<()> {
+        let conn = self.conn()?;
+        if delta >= 0 {
+            conn.execute(
+                "INSERT INTO pots (guild, amount) VALUES (?1, ?2)
+                ON CONFLICT (guild) DO UPDATE SET amount = amount + ?2",
+                params![guild, delta]
+            )?;
+        } else if conn.execute(
+            "UPDATE pots SET amount = amount + ?2 WHERE guild = ?1 AND amount + ?2 >= 0", params![guild, delta]
+        )? != 1 {
+            bail!("the community pot doesn't hold the {} coins to take", -delta);
+        }
+        Ok(())
+    }
+
+    /// Remembers the rake of a resolution that can still be undone
+    pub fn set_rake(&self, bet: u64, guild: u64, amount: u64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO rakes (bet, guild, amount) VALUES (?1, ?2, ?3)",
+            params![bet, guild, amount]
+        )?;
+        Ok(())
+    }
+
+    pub fn rake(&self, bet: u64) -> Result<Option<u64>> {
+        Ok(self.conn()?.query_row(
+            "SELECT amount FROM rakes WHERE bet = ?1",
+            params![bet],
+            |row| row.get(0)
+        ).optional()?)
+    }
+
+    pub fn remove_rake(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("DELETE FROM rakes WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    pub fn set_stake_limits(&self, bet: u64, limits: &StakeLimits) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO stake_limits (bet, min_stake, max_stake, max_pool_share) VALUES (?1, ?2, ?3, ?4)",
+            params![bet, limits.min_stake, limits.max_stake, limits.max_pool_share]
+        )?;
+        Ok(())
+    }
+
+    pub fn stake_limits(&self, bet: u64) -> Result<StakeLimits> {
+        Ok(self.conn()?.query_row(
+            "SELECT min_stake, max_stake, max_pool_share FROM stake_limits WHERE bet = ?1",
+            params![bet],
+            |row| Ok(StakeLimits { min_stake: row.get(0)?, max_stake: row.get(1)?, max_pool_share: row.get(2)? })
+        ).optional()?.unwrap_or_default())
+    }
+
+    /// Takes coins out of a position on an open bet, the position is removed once empty
+    pub fn withdraw_position(&self, bet: u64, user: u64, amount: u64) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE positions SET stake = stake - ?3 WHERE bet = ?1 AND user = ?2", params![bet, user, amount])?;
+        tx.execute("DELETE FROM positions WHERE bet = ?1 AND user = ?2 AND stake <= 0", params![bet, user])?;
+        tx.execute(
+            "DELETE FROM guesses WHERE bet = ?1 AND user = ?2 AND NOT EXISTS (SELECT 1 FROM positions WHERE bet = ?1 AND user = ?2)", 
+            params![bet, user]
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn switch_position(&self, bet: u64, user: u64, outcome: usize, outcome_desc: &str) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE positions SET outcome = ?3, outcome_desc = ?4 WHERE bet = ?1 AND user = ?2",
+            params![bet, user, outcome, outcome_desc]
+        )?;
+        Ok(())
+    }
+
+    /// Sets the descriptions of the outcomes of the bet, the positions taken on them are renamed too
+    pub fn set_bet_outcomes(&self, bet: u64, outcomes: &[String]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        for (outcome, desc) in outcomes.iter().enumerate() {
+            tx.execute(
+                "INSERT OR REPLACE INTO bet_outcomes (bet, outcome, desc) VALUES (?1, ?2, ?3)",
+                params![bet, outcome, desc]
+            )?;
+            tx.execute(
+                "UPDATE positions SET outcome_desc = ?3 WHERE bet = ?1 AND outcome = ?2",
+                params![bet, outcome, desc]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn bet_outcomes(&self, bet: u64) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT desc FROM bet_outcomes WHERE bet = ?1 ORDER BY outcome")?;
+        let rows = stmt.query_map(params![bet], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// (bet, bet desc, outcome, outcome desc) of the open bets of the guild with known outcomes, 
+    /// closest guess bets left out, most recent first
+    pub fn open_bet_outcomes(&self, guild: u64) -> Result<Vec<(u64, String, usize, String)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT b.bet, b.desc, o.outcome, o.desc FROM bet_records b
+            JOIN bet_outcomes o ON o.bet = b.bet
+            WHERE b.guild = ?1 AND b.status = ?2 AND b.bet NOT IN (SELECT bet FROM guess_bets)
+            ORDER BY b.created DESC, o.outcome"
+        )?;
+        let rows = stmt.query_map(
+            params![guild, BetStatus::Open.as_str()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Records a parlay on the (bet, outcome) legs and returns its id
+    pub fn add_parlay(&self, guild: u64, user: u64, amount: u64, legs: &[(u64, usize)], placed: i64) -> Result<u64> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO parlays (guild, user, amount, status, placed) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![guild, user, amount, ParlayStatus::Open.as_str(), placed]
+        )?;
+        let id = tx.last_insert_rowid() as u64;
+        for (bet, outcome) in legs {
+            tx.execute(
+                "INSERT INTO parlay_legs (parlay, bet, outcome) VALUES (?1, ?2, ?3)", params![id, bet, outcome]
+            )?;
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Sets the odds of the legs on the bet, indexed by outcome, legs that already have odds are left as is
+    pub fn set_leg_odds(&self, bet: u64, odds: &[f64]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        for (outcome, odd) in odds.iter().enumerate() {
+            tx.execute(
+                "UPDATE parlay_legs SET odds = ?3 WHERE bet = ?1 AND outcome = ?2 AND odds IS NULL",
+                params![bet, outcome, odd]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Settles the legs on the bet, the outcomes missing from `shares` lost
+    pub fn set_leg_results(&self, bet: u64, shares: &HashMap<usize, f64>) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE parlay_legs SET share = 0 WHERE bet = ?1", params![bet])?;
+        for (outcome, share) in shares {
+            tx.execute(
+                "UPDATE parlay_legs SET share = ?3 WHERE bet = ?1 AND outcome = ?2", params![bet, outcome, share]
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn clear_leg_results(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("UPDATE parlay_legs SET share = NULL WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    /// Every parlay with a leg on the bet
+    pub fn parlays_of_bet(&self, bet: u64) -> Result<Vec<Parlay>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, user, amount, status, payout FROM parlays 
+            WHERE id IN (SELECT parlay FROM parlay_legs WHERE bet = ?1) ORDER BY id"
+        )?;
+        let rows = stmt.query_map(params![bet], |row| Ok((
+            row.get::<_, u64>(0)?, row.get(1)?, row.get(2)?, row.get::<_, String>(3)?, row.get(4)?
+        )))?.collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut legs_stmt = conn.prepare("SELECT odds, share FROM parlay_legs WHERE parlay = ?1")?;
+        let mut parlays = Vec::new();
+        for (id, user, amount, status, payout) in rows {
+            let legs = legs_stmt.query_map(params![id], |row| Ok(ParlayLeg { odds: row.get(0)?, share: row.get(1)? }))?.collect::<rusqlite::Result<_>>()?;
+            parlays.push(Parlay { id, user, amount, status: ParlayStatus::try_from(status.as_str())?, payout, legs });
+        }
+        Ok(parlays)
+    }
+
+    /// Outcomes of the bet that open parlays have a leg on
+    pub fn parlay_outcomes(&self, bet: u64) -> Result<Vec<usize>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT l.outcome FROM parlay_legs l JOIN parlays p ON l.parlay = p.id WHERE l.bet = ?1 AND p.status = ?2"
+        )?;
+        let rows = stmt.query_map(params![bet, ParlayStatus::Open.as_str()], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn set_parlay_status(&self, parlay: u64, status: ParlayStatus, payout: Option<u64>) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE parlays SET status = ?2, payout = ?3 WHERE id = ?1", params![parlay, status.as_str(), payout]
+        )?;
+        Ok(())
+    }
+
+    pub fn add_challenge(&self, challenge: &Challenge) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO challenges (id, guild, channel, challenger, opponent, amount, claim, status, expires) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                challenge.id, challenge.guild, challenge.channel, challenge.challenger, challenge.opponent, 
+                challenge.amount, challenge.claim, challenge.status.as_str(), challenge.expires
+            ]
+        )?;
+        Ok(())
+    }
+
+    pub fn challenge(&self, id: u64) -> Result<Option<Challenge>> {
+        let row = self.conn()?.query_row(
+            "SELECT guild, channel, challenger, opponent, amount, claim, status, expires, 
+            challenger_vote, opponent_vote, winner, payout FROM challenges WHERE id = ?1",
+            params![id],
+            |row| Ok((
+                Challenge {
+                    id, guild: row.get(0)?, channel: row.get(1)?, challenger: row.get(2)?, opponent: row.get(3)?,
+                    amount: row.get(4)?, claim: row.get(5)?, status: ChallengeStatus::Pending, expires: row.get(7)?,
+                    challenger_vote: row.get(8)?, opponent_vote: row.get(9)?, winner: row.get(10)?, payout: row.get(11)?
+                },
+                row.get::<_, String>(6)?
+            ))
+        ).optional()?;
+        row.map(|(challenge, status)| Ok(Challenge { status: ChallengeStatus::try_from(status.as_str())?, ..challenge })).transpose()
+    }
+
+    /// Moves the challenge from one status to another, returns false if it wasn't in the `from` status anymore
+    pub fn transition_challenge(&self, id: u64, from: ChallengeStatus, to: ChallengeStatus) -> Result<bool> {
+        Ok(self.conn()?.execute(
+            "UPDATE challenges SET status = ?3 WHERE id = ?1 AND status = ?2", params![id, from.as_str(), to.as_str()]
+        )? == 1)
+    }
+
+    /// Records who the user says won the challenge
+    pub fn set_challenge_vote(&self, id: u64, user: u64, winner: u64) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE challenges SET 
+                challenger_vote = CASE WHEN challenger = ?2 THEN ?3 ELSE challenger_vote END,
+                opponent_vote = CASE WHEN opponent = ?2 THEN ?3 ELSE opponent_vote END
+            WHERE id = ?1",
+            params![id, user, winner]
+        )?;
+        Ok(())
+    }
+
+    /// Closes the challenge if it's still in the `from` status, with no winner if the stakes were refunded, 
+    /// returns false if it was already moved on
+    pub fn settle_challenge(&self, id: u64, from: ChallengeStatus, winner: Option<u64>, payout: u64) -> Result<bool> {
+        let status = if winner.is_some() { ChallengeStatus::Won } else { ChallengeStatus::Refunded };
+        Ok(self.conn()?.execute(
+            "UPDATE challenges SET status = ?2, winner = ?3, payout = ?4 WHERE id = ?1 AND status = ?5", 
+            params![id, status.as_str(), winner, payout, from.as_str()]
+        )? == 1)
+    }
+
+    /// (challenge, channel) of the pending challenges that weren't accepted in time
+    pub fn expired_challenges(&self, now: i64) -> Result<Vec<(u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT id, channel FROM challenges WHERE status = ?1 AND expires <= ?2")?;
+        let rows = stmt.query_map(
+            params![ChallengeStatus::Pending.as_str(), now], |row| Ok((row.get(0)?, row.get(1)?))
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(id: u64) -> Challenge {
+        Challenge {
+            id, guild: 1, channel: 2, challenger: 10, opponent: 20, amount: 50, claim: "claim".to_string(), 
+            status: ChallengeStatus::Pending, expires: 0, challenger_vote: None, opponent_vote: None, winner: None, payout: None
+        }
+    }
+
+    #[test]
+    fn pot_cant_go_negative() {
+        let db = BotDB::new(":memory:").unwrap();
+        assert!(db.add_to_pot(1, -1).is_err());
+        db.add_to_pot(1, 10).unwrap();
+        db.add_to_pot(1, -4).unwrap();
+        assert!(db.add_to_pot(1, -7).is_err());
+        assert_eq!(db.pot(1).unwrap(), 6);
+        db.add_to_pot(1, -6).unwrap();
+        assert_eq!(db.pot(1).unwrap(), 0);
+    }
+
+    #[test]
+    fn daily_is_claimed_once_a_day() {
+        let db = BotDB::new(":memory:").unwrap();
+        assert!(db.claim_daily(1, 10, 100, 1, 0).unwrap());
+        assert!(!db.claim_daily(1, 10, 150, 1, 0).unwrap());
+        assert!(db.claim_daily(1, 10, 200, 2, 180).unwrap());
+        assert_eq!(db.daily(1, 10).unwrap(), Some((200, 2)));
+    }
+
+    #[test]
+    fn rebuy_respects_the_cooldown() {
+        let db = BotDB::new(":memory:").unwrap();
+        assert!(db.add_bankruptcy(1, 10, 100, 100).unwrap());
+        assert!(!db.add_bankruptcy(1, 10, 100, 100).unwrap());
+        assert!(db.add_bankruptcy(1, 10, 200, 150).unwrap());
+        assert_eq!(db.bankruptcy(1, 10).unwrap(), Some((2, 200)));
+    }
+
+    #[test]
+    fn challenge_transitions_are_gated_on_the_status() {
+        let db = BotDB::new(":memory:").unwrap();
+        db.add_challenge(&challenge(1)).unwrap();
+        assert!(db.transition_challenge(1, ChallengeStatus::Pending, ChallengeStatus::Accepted).unwrap());
+        // a second accept, or an expiry racing the accept, finds it already moved on
+        assert!(!db.transition_challenge(1, ChallengeStatus::Pending, ChallengeStatus::Accepted).unwrap());
+        assert!(!db.transition_challenge(1, ChallengeStatus::Pending, ChallengeStatus::Expired).unwrap());
+        assert_eq!(db.challenge(1).unwrap().unwrap().status, ChallengeStatus::Accepted);
+    }
+
+    #[test]
+    fn challenge_settles_once() {
+        let db = BotDB::new(":memory:").unwrap();
+        db.add_challenge(&challenge(1)).unwrap();
+        assert!(!db.settle_challenge(1, ChallengeStatus::Accepted, Some(10), 100).unwrap());
+        db.transition_challenge(1, ChallengeStatus::Pending, ChallengeStatus::Accepted).unwrap();
+        assert!(db.settle_challenge(1, ChallengeStatus::Accepted, Some(10), 100).unwrap());
+        assert!(!db.settle_challenge(1, ChallengeStatus::Accepted, None, 0).unwrap());
+        let settled = db.challenge(1).unwrap().unwrap();
+        assert_eq!((settled.status, settled.winner, settled.payout), (ChallengeStatus::Won, Some(10), Some(100)));
+    }
+
+    #[test]
+    fn escrowed_counts_what_the_bot_holds() {
+        let db = BotDB::new(":memory:").unwrap();
+        db.add_bet_record(100, 1, 10, "open", 0).unwrap();
+        db.add_bookmaker(100, &Bookmaker { author: 10, bankroll: 30, odds: vec![2., 2.] }).unwrap();
+        db.add_bet_record(101, 1, 10, "resolved", 0).unwrap();
+        db.add_bookmaker(101, &Bookmaker { author: 10, bankroll: 1000, odds: vec![2., 2.] }).unwrap();
+        db.set_bet_status(101, BetStatus::Resolved).unwrap();
+        db.add_challenge(&challenge(1)).unwrap();
+        assert_eq!(db.escrowed(1, 10).unwrap(), 30);
+        db.transition_challenge(1, ChallengeStatus::Pending, ChallengeStatus::Accepted).unwrap();
+        db.add_parlay(1, 10, 7, &[(100, 0), (102, 1)], 0).unwrap();
+        db.add_bet_record(103, 1, 20, "undone", 0).unwrap();
+        db.set_escrow(103, &vec![Wager { user: 10, outcome: 0, amount: 5 }, Wager { user: 20, outcome: 1, amount: 9 }]).unwrap();
+        assert_eq!(db.escrowed(1, 10).unwrap(), 30 + 50 + 7 + 5);
+        assert_eq!(db.escrowed(1, 20).unwrap(), 50 + 9);
+        assert_eq!(db.escrowed(2, 10).unwrap(), 0);
+    }
+}
@@ -1,38 +1,179 @@
+use anyhow::{Result, bail};
 use serde::{Serialize, Deserialize};
 use lazy_static::lazy_static;
 use confy;
+use std::cmp::{max, min};
+use crate::payouts::GuessPayout;
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 struct PartialConfig {
     pub currency: String,
     pub starting_coins: u32,
     pub income: u32,
+    pub undo_window: u32,
+    pub daily: u32,
+    pub daily_streak_bonus: u32,
+    pub daily_max_streak: u32,
+    pub rebuy: u32,
+    pub rebuy_cooldown: u32,
+    pub hide_bankrupt: bool,
+    pub guess_payout: String,
+    pub rake: u32,
+    pub min_stake: u32,
+    pub max_stake: u32,
+    pub max_pool_share: u32,
+    pub withdraw_penalty: u32,
+    pub bet_threads: bool,
+    pub compact_bets: bool,
 }
 
 impl Default for PartialConfig {
     fn default() -> Self {
         Self { 
-            currency: "💵".to_string(), starting_coins: 100, income: 5
+            currency: "💵".to_string(), starting_coins: 100, income: 5, undo_window: 5,
+            daily: 20, daily_streak_bonus: 10, daily_max_streak: 7,
+            rebuy: 50, rebuy_cooldown: 24, hide_bankrupt: false, guess_payout: "winner".to_string(),
+            rake: 0, min_stake: 0, max_stake: 0, max_pool_share: 100,
+            withdraw_penalty: 0, bet_threads: false, compact_bets: false
         }
     }
 }
 
+/// The settings that can be overriden per guild with /config
+pub const SETTINGS: [&str; 18] = [
+    "currency", "starting_coins", "income", "undo_window", "daily", "daily_streak_bonus", "daily_max_streak",
+    "rebuy", "rebuy_cooldown", "hide_bankrupt", "guess_payout", "rake", "min_stake", "max_stake", "max_pool_share",
+    "withdraw_penalty", "bet_threads", "compact_bets"
+];
+
+/// The longest currency that can be set, so it doesn't flood every message it's shown in
+const MAX_CURRENCY_LEN: usize = 32;
+
+#[derive(Clone)]
 pub struct Config {
     pub currency: String,
     pub starting_coins: u32,
     pub income: u32,
+    /// minutes during which a resolution can be undone
+    pub undo_window: u32,
+    /// coins given by /daily
+    pub daily: u32,
+    /// % added to the daily coins for each consecutive day claimed
+    pub daily_streak_bonus: u32,
+    /// streak after which the bonus stops growing
+    pub daily_max_streak: u32,
+    /// coins given by /rebuy to a bankrupt user
+    pub rebuy: u32,
+    /// hours between 2 rebuys
+    pub rebuy_cooldown: u32,
+    /// leave users that went bankrupt out of the leaderboard instead of marking them
+    pub hide_bankrupt: bool,
+    /// default payout of closest guess bets
+    pub guess_payout: GuessPayout,
+    /// % of the pool of every resolved bet that goes to the community pot
+    pub rake: u32,
+    /// smallest position a user can hold on a bet
+    pub min_stake: u32,
+    /// biggest position a user can hold on a bet, 0 for no limit
+    pub max_stake: u32,
+    /// % of the pool a single user can hold
+    pub max_pool_share: u32,
+    /// % of the coins withdrawn from an open bet that goes to the community pot
+    pub withdraw_penalty: u32,
+    /// post the outcomes of each bet in a thread of its own
+    pub bet_threads: bool,
+    /// render each bet in a single message, with menus to pick an outcome instead of one message per outcome
+    pub compact_bets: bool,
 }
 
-impl Config {
-    fn from(part_cfg: PartialConfig) -> Self {
-        Self {
+impl TryFrom<PartialConfig> for Config {
+    type Error = anyhow::Error;
+
+    fn try_from(part_cfg: PartialConfig) -> Result<Self, Self::Error> {
+        Ok(Self {
             currency: part_cfg.currency,
             starting_coins: part_cfg.starting_coins,
             income: part_cfg.income,
+            undo_window: part_cfg.undo_window,
+            daily: part_cfg.daily,
+            daily_streak_bonus: part_cfg.daily_streak_bonus,
+            daily_max_streak: part_cfg.daily_max_streak,
+            rebuy: part_cfg.rebuy,
+            rebuy_cooldown: part_cfg.rebuy_cooldown,
+            hide_bankrupt: part_cfg.hide_bankrupt,
+            guess_payout: GuessPayout::try_from(part_cfg.guess_payout.as_str())?,
+            rake: part_cfg.rake,
+            min_stake: part_cfg.min_stake,
+            max_stake: part_cfg.max_stake,
+            max_pool_share: part_cfg.max_pool_share,
+            withdraw_penalty: part_cfg.withdraw_penalty,
+            bet_threads: part_cfg.bet_threads,
+            compact_bets: part_cfg.compact_bets,
+        })
+    }
+}
+
+impl Config {
+    /// Coins given by /daily on the nth consecutive day
+    pub fn daily_amount(&self, streak: u32) -> u64 {
+        let bonus_days = min(streak, max(self.daily_max_streak, 1)).saturating_sub(1) as u64;
+        self.daily as u64 * (100 + bonus_days*self.daily_streak_bonus as u64) / 100
+    }
+
+    /// Overrides a setting from its name and textual value
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "currency" => {
+                if value.trim().is_empty() {
+                    bail!("the currency can't be empty");
+                }
+                if value.trim().chars().count() > MAX_CURRENCY_LEN {
+                    bail!("the currency can't be longer than {} characters", MAX_CURRENCY_LEN);
+                }
+                self.currency = value.trim().to_string();
+            },
+            "starting_coins" => self.starting_coins = value.trim().parse()?,
+            "income" => self.income = value.trim().parse()?,
+            "undo_window" => self.undo_window = value.trim().parse()?,
+            "daily" => self.daily = value.trim().parse()?,
+            "daily_streak_bonus" => self.daily_streak_bonus = value.trim().parse()?,
+            "daily_max_streak" => self.daily_max_streak = value.trim().parse()?,
+            "rebuy" => self.rebuy = value.trim().parse()?,
+            "rebuy_cooldown" => self.rebuy_cooldown = value.trim().parse()?,
+            "hide_bankrupt" => self.hide_bankrupt = value.trim().parse()?,
+            "guess_payout" => self.guess_payout = GuessPayout::try_from(value)?,
+            "rake" => {
+                let rake = value.trim().parse()?;
+                if rake > 100 {
+                    bail!("the rake is a percentage, it can't be more than 100");
+                }
+                self.rake = rake;
+            },
+            "min_stake" => self.min_stake = value.trim().parse()?,
+            "max_stake" => self.max_stake = value.trim().parse()?,
+            "max_pool_share" => {
+                let share = value.trim().parse()?;
+                if share == 0 || share > 100 {
+                    bail!("the max pool share is a percentage between 1 and 100");
+                }
+                self.max_pool_share = share;
+            },
+            "withdraw_penalty" => {
+                let penalty = value.trim().parse()?;
+                if penalty > 100 {
+                    bail!("the withdraw penalty is a percentage, it can't be more than 100");
+                }
+                self.withdraw_penalty = penalty;
+            },
+            "bet_threads" => self.bet_threads = value.trim().parse()?,
+            "compact_bets" => self.compact_bets = value.trim().parse()?,
+            _ => bail!("unknown setting '{}'", key)
         }
+        Ok(())
     }
 }
 
 lazy_static! {
-    pub static ref config: Config = Config::from(confy::load_path("./config.toml").unwrap());
+    pub static ref config: Config = Config::try_from(confy::load_path::<PartialConfig>("./config.toml").unwrap()).unwrap();
 }
@@ -1,8 +1,8 @@
-use betting::{Bet, Outcome};
+use betting::{AccountStatus, Bet, Outcome};
 use betting::utils::lrm;
-use crate::config::config;
-use itertools;
-use std::cmp::min;
+use crate::{bot_db::{AuditEntry, BetStatus, Challenge, ChallengeStatus, HistoryEntry, Standing}, config::Config, stats::UserStats};
+use itertools::{self, Itertools};
+use std::{cmp::min, collections::HashMap};
 
 const NUM_SUFFIX: [&str; 5] = ["", "K", "M", "B", "T"];
 
@@ -35,30 +35,31 @@ fn outcome_stub(outcome_desc: &String) -> Outcome {
     }
 }
 
-pub fn bet_stub(outcomes_desc: &Vec<String>) -> Bet {
+pub fn bet_stub(bet: u64, server: u64, outcomes_desc: &Vec<String>) -> Bet {
     Bet {
-        bet: 0,
+        bet,
         desc: String::new(),
         outcomes: outcomes_desc.iter().map(outcome_stub).collect(),
-        server: 0,
+        server,
         author: 0,
         is_open: true
     }
 }
 
-fn outcome_display(desc: &str, percent: u64, odd: f32, sum: u32, people: u32) -> String {
+fn outcome_display(desc: &str, percent: u64, odd: f32, sum: u32, people: u32, currency: &str) -> String {
     format!(
         "## > {}\n` {: >3}%  | {: >6} 🏆  {: >4} {}  {: >4} 👥 `",
         desc.trim().trim_start_matches("#"),
         percent,
         "1:".to_string() + &number_display(if odd.is_nan() { 1. } else { odd }),
         number_display(sum),
-        config.currency,
+        currency,
         number_display(people)
     )
 }
 
-pub fn outcomes_display(bet_status: &Bet) -> Vec<String> {
+/// Fixed odds replace the parimutuel ones when the bet has a bookmaker
+pub fn outcomes_display(bet_status: &Bet, fixed_odds: Option<&[f64]>, currency: &str) -> Vec<String> {
     let sums: Vec<u64> = bet_status
         .outcomes
         .iter()
@@ -74,7 +75,10 @@ pub fn outcomes_display(bet_status: &Bet) -> Vec<String> {
 
     let percents = lrm(100, &sums);
 
-    let odds: Vec<f32> = sums.iter().map(|sum| total as f32 / *sum as f32).collect();
+    let odds: Vec<f32> = match fixed_odds {
+        Some(fixed_odds) => fixed_odds.iter().map(|odd| *odd as f32).collect(),
+        None => sums.iter().map(|sum| total as f32 / *sum as f32).collect(),
+    };
 
     let peoples: Vec<usize> = bet_status
         .outcomes
@@ -84,16 +88,221 @@ pub fn outcomes_display(bet_status: &Bet) -> Vec<String> {
 
     itertools::izip!(&bet_status.outcomes, percents, odds, sums, peoples)
         .map(|(outcome, percent, odd, sum, people)| {
-            outcome_display(&outcome.desc, percent, odd, sum as u32, people as u32)
+            outcome_display(&outcome.desc, percent, odd, sum as u32, people as u32, currency)
         })
         .collect()
 }
 
+const MAX_GUESSES_SHOWN: usize = 30;
+
+/// Live list of the guesses of a closest guess bet as (user, guess, stake)
+pub fn guesses_display(mut guesses: Vec<(u64, f64, u64)>, currency: &str) -> String {
+    if guesses.is_empty() {
+        return "## > Guesses\n*No guess yet*".to_string();
+    }
+    guesses.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let total: u64 = guesses.iter().map(|(_, _, stake)| stake).sum();
+    format!("## > Guesses\n` {: >4} {}  {: >4} 👥 `\n", number_display(total as f64), currency, number_display(guesses.len() as f64))
+    + &guesses.iter().take(MAX_GUESSES_SHOWN).map(|(user, guess, stake)| 
+        format!("`{}` <@{}> {} {}", guess, user, stake, currency)
+    ).join("\n")
+    + &if guesses.len() > MAX_GUESSES_SHOWN { format!("\n-# and {} more", guesses.len() - MAX_GUESSES_SHOWN) } else { String::new() }
+}
+
 pub fn shorten(text: &str, length: usize) -> String {
     let res = text.split_once("\n").and_then(|(first, _)| Some(first)).unwrap_or(text);
-    if res.len() > length {
-        res.split_at(length-1).0.to_owned() + "…"
+    if res.chars().count() > length {
+        res.chars().take(length-1).collect::<String>() + "…"
     } else {
         res.to_owned()
     }
-}
\ No newline at end of file
+}
+
+/// Sorts the accounts from best to worst
+pub fn ranking(accounts: &mut [AccountStatus]) {
+    // sort by balance+inbet first and balance to tie break
+    accounts.sort_by_key(|acc| (acc.balance+acc.in_bet, acc.balance));
+    accounts.reverse();
+}
+
+fn bankruptcy_display(count: u32) -> String {
+    match count {
+        0 => String::new(),
+        1 => "  💀".to_string(),
+        n => format!("  💀x{}", n),
+    }
+}
+
+pub fn leaderboard_display(mut accounts: Vec<AccountStatus>, bankruptcies: &HashMap<u64, u32>, hide_bankrupt: bool, currency: &str) -> String {
+    if hide_bankrupt {
+        accounts.retain(|acc| !bankruptcies.contains_key(&acc.user));
+    }
+    ranking(&mut accounts);
+    format!("{}  ({} in bet)   user\n", currency, currency) 
+    + &accounts.into_iter().take(10).map(|acc| format!(
+        "{}  ({})   <@{}>{}", acc.balance, acc.in_bet, acc.user, 
+        bankruptcy_display(bankruptcies.get(&acc.user).copied().unwrap_or(0))
+    )).join("\n") + "\n..."
+}
+
+pub fn season_display(season: u32, ended: i64, standings: &[Standing], currency: &str) -> String {
+    format!("## Season {}\n-# ended <t:{}:d>\n{}  ({} in bet)   user\n", season, ended, currency, currency)
+    + &standings.iter().take(10).enumerate().map(|(i, standing)| format!(
+        "#{}  {}  ({})   <@{}>", i+1, standing.balance, standing.in_bet, standing.user
+    )).join("\n") + "\n..."
+}
+
+/// Line of a resolution message telling how much was raked, if anything
+pub fn rake_display(rake: u64, currency: &str) -> String {
+    if rake == 0 {
+        return String::new();
+    }
+    format!("\n-# 🏦 {} {} raked into the community pot", rake, currency)
+}
+
+/// Lines of a resolution message announcing the parlays it made win
+pub fn parlays_display(parlays: &[(u64, u64)], currency: &str) -> String {
+    parlays.iter().map(|(user, payout)| format!("\n-# 🎰 <@{}> won {} {} with a parlay", user, payout, currency)).collect()
+}
+
+/// Recap of a parlay placed on the (bet desc, outcome desc) legs
+pub fn parlay_display(amount: u64, legs: &[(&str, &str)], currency: &str) -> String {
+    format!("🎰 Parlay of {} {} placed on:\n", amount, currency) 
+    + &legs.iter().map(|(desc, outcome_desc)| format!("> **{}** → {}", outcome_desc, desc)).join("\n")
+    + "\n-# each leg's odds are fixed when its bet is locked"
+}
+
+pub fn challenge_display(challenge: &Challenge, currency: &str) -> String {
+    let vote_display = |user: u64, vote: Option<u64>| vote
+        .map(|winner| format!("\n-# <@{}> says <@{}> won", user, winner))
+        .unwrap_or_default();
+    format!(
+        "## ⚔️ <@{}> challenges <@{}>\n> {}\n{} {} each\n", 
+        challenge.challenger, challenge.opponent, challenge.claim, challenge.amount, currency
+    ) + &match challenge.status {
+        ChallengeStatus::Pending => format!("-# waiting for <@{}> to accept, expires <t:{}:R>", challenge.opponent, challenge.expires),
+        ChallengeStatus::Accepted => format!(
+            "-# 🔒 {} {} in escrow, both sides confirm the winner{}{}", 
+            2*challenge.amount, currency,
+            vote_display(challenge.challenger, challenge.challenger_vote), vote_display(challenge.opponent, challenge.opponent_vote)
+        ),
+        ChallengeStatus::Disputed => format!(
+            "-# ⚠️ both sides disagree, an admin has to arbitrate{}{}",
+            vote_display(challenge.challenger, challenge.challenger_vote), vote_display(challenge.opponent, challenge.opponent_vote)
+        ),
+        ChallengeStatus::Won => format!(
+            "🏆 <@{}> won {} {}", challenge.winner.unwrap_or_default(), challenge.payout.unwrap_or_default(), currency
+        ),
+        ChallengeStatus::Refunded => "*Challenge called off, both stakes have been refunded.*".to_string(),
+        ChallengeStatus::Declined => "*Challenge declined.*".to_string(),
+        ChallengeStatus::Expired => "*Challenge expired.*".to_string(),
+    }
+}
+
+fn history_entry_display(entry: &HistoryEntry, currency: &str) -> String {
+    match entry {
+        HistoryEntry::Position { desc, status, outcome_desc, stake, payout, placed } => {
+            let result = match (status, payout) {
+                (BetStatus::Aborted, _) => "refunded".to_string(),
+                (_, Some(0)) => "lost".to_string(),
+                (_, Some(payout)) => format!("won {} {}", payout, currency),
+                (BetStatus::Open, None) => "open".to_string(),
+                (_, None) => "locked".to_string(),
+            };
+            format!(
+                "<t:{}:d> **{}**\n> {}: {} {} → {}",
+                placed, shorten(desc.trim().trim_start_matches("#"), 50), 
+                shorten(outcome_desc, 30), stake, currency, result
+            )
+        },
+        HistoryEntry::Transfer { sender, recipient, amount, note, at } => format!(
+            "<t:{}:d> 🎁 <@{}> gave {} {} to <@{}>{}",
+            at, sender, amount, currency, recipient, 
+            if note.is_empty() { String::new() } else { format!(": {}", shorten(note, 50)) }
+        ),
+    }
+}
+
+pub fn history_display(user: u64, entries: &[HistoryEntry], page: usize, pages: usize, currency: &str) -> String {
+    if entries.is_empty() {
+        return format!("<@{}> hasn't bet or given anything yet", user);
+    }
+    format!("## History of <@{}>\n", user) 
+    + &entries.iter().map(|entry| history_entry_display(entry, currency)).join("\n")
+    + &format!("\n-# page {}/{}", page+1, pages)
+}
+
+fn percent_display(ratio: f64) -> String {
+    if ratio.is_finite() { format!("{:.0}%", ratio*100.) } else { "-".to_string() }
+}
+
+pub fn user_stats_display(user: u64, stats: &UserStats, currency: &str) -> String {
+    format!(
+        "## Stats of <@{}>\n\
+        Bets placed: {} ({} resolved)\n\
+        Win rate: {}\n\
+        Net profit: {} {}\n\
+        ROI: {}\n\
+        Biggest payout: {} {}\n\
+        Longest win streak: {}\n\
+        Favourite bet author: {}",
+        user, stats.bets, stats.resolved, percent_display(stats.win_rate()), 
+        stats.profit, currency, percent_display(stats.roi()),
+        stats.biggest_payout, currency, stats.longest_streak,
+        stats.favourite_author.map(|(author, n)| format!("<@{}> ({} bets)", author, n)).unwrap_or("-".to_string())
+    )
+}
+
+pub fn guild_stats_display(bets: u64, bettors: u64, volume: u64, popular_bets: &[(String, u64, u64)], currency: &str) -> String {
+    format!(
+        "## Server stats\n{} bets, {} bettors, {} {} bet in total\n### Most popular bets\n",
+        bets, bettors, number_display(volume as f64), currency
+    ) + &popular_bets.iter().map(|(desc, bettors, volume)| format!(
+        "> {}\n{} 👥  {} {}", shorten(desc.trim().trim_start_matches("#"), 50), bettors, number_display(*volume as f64), currency
+    )).join("\n")
+}
+
+pub fn config_display(cfg: &Config) -> String {
+    format!(
+        "## Server settings\n\
+        currency: {}\n\
+        starting_coins: {}\n\
+        income: {}\n\
+        undo_window: {} min\n\
+        daily: {}\n\
+        daily_streak_bonus: {}%\n\
+        daily_max_streak: {} days\n\
+        rebuy: {}\n\
+        rebuy_cooldown: {} h\n\
+        hide_bankrupt: {}\n\
+        guess_payout: {}\n\
+        rake: {}%\n\
+        min_stake: {}\n\
+        max_stake: {}\n\
+        max_pool_share: {}%\n\
+        withdraw_penalty: {}%\n\
+        bet_threads: {}\n\
+        compact_bets: {}",
+        cfg.currency, cfg.starting_coins, cfg.income, cfg.undo_window, 
+        cfg.daily, cfg.daily_streak_bonus, cfg.daily_max_streak,
+        cfg.rebuy, cfg.rebuy_cooldown, cfg.hide_bankrupt, cfg.guess_payout.as_str(), cfg.rake, 
+        cfg.min_stake, if cfg.max_stake == 0 { "none".to_string() } else { cfg.max_stake.to_string() }, cfg.max_pool_share,
+        cfg.withdraw_penalty, cfg.bet_threads, cfg.compact_bets
+    )
+}
+
+/// How much of an adjustment reason is shown in the audit log, so a page fits in a message
+const MAX_AUDIT_REASON_LEN: usize = 70;
+
+pub fn audit_display(entries: &[AuditEntry], page: usize, pages: usize, currency: &str) -> String {
+    if entries.is_empty() {
+        return "No manual adjustment has been made yet".to_string();
+    }
+    "## Manual adjustments\n".to_string()
+    + &entries.iter().map(|entry| format!(
+        "<t:{}:d> <@{}> {} {} {} <@{}>: {}",
+        entry.at, entry.admin, if entry.delta >= 0 { "granted" } else { "took" }, 
+        entry.delta.unsigned_abs(), currency, entry.user, shorten(&entry.reason, MAX_AUDIT_REASON_LEN)
+    )).join("\n")
+    + &format!("\n-# page {}/{}", page+1, pages)
+}
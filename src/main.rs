@@ -1,9 +1,13 @@
 mod config;
+mod bot_db;
 mod serialize_utils;
 mod front_utils;
+mod payouts;
 mod betting_bot;
 mod betting_commands;
 mod betting_events;
+mod scheduler;
+mod stats;
 use betting_bot::BettingBot;
 use env_logger;
 use log::{warn, LevelFilter};
@@ -0,0 +1,421 @@
+use anyhow::bail;
+use betting::utils::lrm;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wager {
+    pub user: u64,
+    pub outcome: usize,
+    pub amount: u64,
+}
+
+/// How the pool of a closest guess bet is shared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuessPayout {
+    /// the closest guess takes everything
+    Winner,
+    /// the 3 closest guesses get 50%, 30% and 20%
+    Top3,
+    /// everyone gets a share that grows the closer they were
+    Proportional,
+}
+
+pub const GUESS_PAYOUTS: [GuessPayout; 3] = [GuessPayout::Winner, GuessPayout::Top3, GuessPayout::Proportional];
+const TOP3_TIERS: [u64; 3] = [50, 30, 20];
+
+impl GuessPayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GuessPayout::Winner => "winner",
+            GuessPayout::Top3 => "top3",
+            GuessPayout::Proportional => "proportional",
+        }
+    }
+}
+
+impl TryFrom<&str> for GuessPayout {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value.trim() {
+            "winner" => GuessPayout::Winner,
+            "top3" => GuessPayout::Top3,
+            "proportional" => GuessPayout::Proportional,
+            _ => bail!("'{}' is not a payout, expecting winner, top3 or proportional", value)
+        })
+    }
+}
+
+pub struct Split {
+    /// net balance change of every participant
+    pub diffs: Vec<(u64, i64)>,
+    /// what is paid to the bettors of each winning outcome
+    pub payouts: Vec<(usize, u64)>,
+}
+
+/// Balance changes applied when paying the winners of a bet
+pub struct Settlement {
+    pub diffs: Vec<(u64, i64)>,
+    pub payouts: Vec<(usize, u64)>,
+    /// positions on the bet before it was settled
+    pub wagers: Vec<Wager>,
+    /// taken from the pool for the community pot
+    pub rake: u64,
+    /// (user, payout) of the parlays that this resolution made win
+    pub parlays: Vec<(u64, u64)>,
+}
+
+/// Shares the whole pool between the winning outcomes proportionally to their weights,
+/// then between the bettors of each outcome proportionally to their stake.
+/// Winning outcomes nobody bet on are skipped, if no winner bet at all everyone gets refunded.
+pub fn split_pool(wagers: &[Wager], weights: &[(usize, u64)]) -> Split {
+    let total: u64 = wagers.iter().map(|wager| wager.amount).sum();
+    let outcome_stake = |outcome: usize| -> u64 {
+        wagers.iter().filter(|wager| wager.outcome == outcome).map(|wager| wager.amount).sum()
+    };
+    let weights: Vec<(usize, u64)> = weights.iter()
+        .map(|(outcome, weight)| (*outcome, if outcome_stake(*outcome) > 0 { *weight } else { 0 }))
+        .collect();
+    if weights.iter().all(|(_, weight)| *weight == 0) {
+        return Split {
+            diffs: wagers.iter().map(|wager| (wager.user, 0)).collect(),
+            payouts: weights.iter().map(|(outcome, _)| (*outcome, 0)).collect(),
+        };
+    }
+    let pools = lrm(total, &weights.iter().map(|(_, weight)| *weight).collect());
+    let mut diffs: Vec<(u64, i64)> = wagers.iter().map(|wager| (wager.user, -(wager.amount as i64))).collect();
+    for ((outcome, _), pool) in weights.iter().zip(pools.iter()) {
+        let winners: Vec<usize> = (0..wagers.len()).filter(|i| wagers[*i].outcome == *outcome).collect();
+        if winners.is_empty() {
+            continue;
+        }
+        let shares = lrm(*pool, &winners.iter().map(|i| wagers[*i].amount).collect());
+        for (i, share) in winners.into_iter().zip(shares) {
+            diffs[i].1 += share as i64;
+        }
+    }
+    Split {
+        diffs,
+        payouts: weights.iter().map(|(outcome, _)| *outcome).zip(pools).collect(),
+    }
+}
+
+/// Net balance change of every guesser once the true value is known, 
+/// ties share their tier proportionally to their stake
+pub fn guess_split(wagers: &[Wager], guesses: &HashMap<u64, f64>, truth: f64, payout: GuessPayout) -> Vec<(u64, i64)> {
+    let total: u64 = wagers.iter().map(|wager| wager.amount).sum();
+    let distances: Vec<f64> = wagers.iter()
+        .map(|wager| guesses.get(&wager.user).map(|guess| (guess - truth).abs()).unwrap_or(f64::INFINITY))
+        .collect();
+    let shares = match payout {
+        GuessPayout::Winner => tiered_shares(wagers, &distances, total, &[1]),
+        GuessPayout::Top3 => tiered_shares(wagers, &distances, total, &TOP3_TIERS),
+        GuessPayout::Proportional => {
+            // the furthest guess gets nothing, the others get more the closer they were
+            let furthest = distances.iter().cloned().filter(|distance| distance.is_finite()).fold(0., f64::max);
+            let weights: Vec<u64> = wagers.iter().zip(&distances).map(|(wager, distance)| 
+                if furthest > 0. && distance.is_finite() { 
+                    (wager.amount as f64 * (1. - distance/furthest) * 1000.) as u64 
+                } else { 0 }
+            ).collect();
+            if weights.iter().all(|weight| *weight == 0) {
+                wagers.iter().map(|wager| wager.amount).collect()
+            } else {
+                lrm(total, &weights)
+            }
+        }
+    };
+    wagers.iter().zip(shares).map(|(wager, share)| (wager.user, share as i64 - wager.amount as i64)).collect()
+}
+
+/// Splits the pool between the closest distances according to the tiers
+fn tiered_shares(wagers: &[Wager], distances: &[f64], total: u64, tiers: &[u64]) -> Vec<u64> {
+    let mut ranks: Vec<f64> = distances.iter().cloned().filter(|distance| distance.is_finite()).collect();
+    ranks.sort_by(|a, b| a.total_cmp(b));
+    ranks.dedup();
+    ranks.truncate(tiers.len());
+    if ranks.is_empty() {
+        return wagers.iter().map(|wager| wager.amount).collect();
+    }
+    let pools = lrm(total, &tiers[..ranks.len()].to_vec());
+    let mut shares = vec![0; wagers.len()];
+    for (rank, pool) in ranks.iter().zip(pools) {
+        let winners: Vec<usize> = (0..wagers.len()).filter(|i| distances[*i] == *rank).collect();
+        let split = lrm(pool, &winners.iter().map(|i| wagers[*i].amount).collect());
+        for (i, share) in winners.into_iter().zip(split) {
+            shares[i] += share;
+        }
+    }
+    shares
+}
+
+/// How much more can be staked on the outcome of a fixed odds bet before the bankroll can't cover its payout
+pub fn odds_capacity(wagers: &[Wager], odds: &[f64], bankroll: u64, outcome: usize) -> u64 {
+    let total: u64 = wagers.iter().map(|wager| wager.amount).sum();
+    let stake: u64 = wagers.iter().filter(|wager| wager.outcome == outcome).map(|wager| wager.amount).sum();
+    // the stake also goes into the pool, so only the profit of the winners has to be covered
+    let cover = (total + bankroll) as f64 - stake as f64 * odds[outcome];
+    if cover <= 0. { 0 } else { (cover / (odds[outcome] - 1.)).floor() as u64 }
+}
+
+/// What each bettor receives when the outcome wins at fixed odds
+pub fn fixed_odds_payouts(wagers: &[Wager], odds: &[f64], winner: usize) -> Vec<(u64, i64)> {
+    wagers.iter().map(|wager| (
+        wager.user, 
+        if wager.outcome == winner { (wager.amount as f64 * odds[winner]).floor() as i64 } else { 0 }
+    )).collect()
+}
+
+/// Takes `percent` of what the bettors won beyond their own stake from what they received, 
+/// refunds and the return of a bookmaker (who has no stake) aren't raked. Returns what was taken from each
+pub fn take_rake(received: &mut [(u64, i64)], wagers: &[Wager], percent: u64) -> Vec<(u64, u64)> {
+    let mut stakes: HashMap<u64, i64> = HashMap::new();
+    for wager in wagers {
+        *stakes.entry(wager.user).or_default() += wager.amount as i64;
+    }
+    let mut winnings: Vec<(u64, u64)> = Vec::new();
+    for (user, _) in received.iter() {
+        let Some(stake) = stakes.get(user) else {
+            continue;
+        };
+        if winnings.iter().any(|(winner, _)| winner == user) {
+            continue;
+        }
+        let got: i64 = received.iter().filter(|(other, _)| other == user).map(|(_, diff)| diff).sum();
+        if got > *stake {
+            winnings.push((*user, (got - stake) as u64));
+        }
+    }
+    let rake = winnings.iter().map(|(_, won)| won).sum::<u64>()*percent/100;
+    if rake == 0 {
+        return vec![];
+    }
+    let shares = lrm(rake, &winnings.iter().map(|(_, won)| *won).collect());
+    winnings.into_iter().zip(shares).filter(|(_, share)| *share > 0).map(|((user, _), share)| {
+        // a user can receive from several positions, the share is taken from them in turn
+        let mut left = share as i64;
+        for (_, diff) in received.iter_mut().filter(|(other, diff)| *other == user && *diff > 0) {
+            let cut = left.min(*diff);
+            *diff -= cut;
+            left -= cut;
+        }
+        (user, share)
+    }).collect()
+}
+
+/// Whether the bankroll of a fixed odds bet covers the payout of every outcome
+pub fn bankroll_covers(wagers: &[Wager], odds: &[f64], bankroll: u64) -> bool {
+    let total: u64 = wagers.iter().map(|wager| wager.amount).sum();
+    (0..odds.len()).all(|outcome| {
+        let stake: u64 = wagers.iter().filter(|wager| wager.outcome == outcome).map(|wager| wager.amount).sum();
+        stake as f64 * odds[outcome] <= (total + bankroll) as f64
+    })
+}
+
+/// Parimutuel odds of each outcome, stake included, outcomes nobody bet on are at 1
+pub fn pool_odds(wagers: &[Wager], outcomes: usize) -> Vec<f64> {
+    let total: u64 = wagers.iter().map(|wager| wager.amount).sum();
+    (0..outcomes).map(|outcome| {
+        let stake: u64 = wagers.iter().filter(|wager| wager.outcome == outcome).map(|wager| wager.amount).sum();
+        if stake == 0 { 1. } else { total as f64 / stake as f64 }
+    }).collect()
+}
+
+/// Share of what was paid that went to each winning outcome, 
+/// they all get a full share if nothing was paid (everyone was refunded)
+pub fn outcome_shares(payouts: &[(usize, u64)]) -> HashMap<usize, f64> {
+    let total: u64 = payouts.iter().map(|(_, payout)| payout).sum();
+    payouts.iter().map(|(outcome, payout)| (
+        *outcome, if total == 0 { 1. } else { *payout as f64 / total as f64 }
+    )).collect()
+}
+
+/// What a parlay pays given the (odds, share) of each of its legs
+pub fn parlay_payout(amount: u64, legs: &[(f64, f64)]) -> u64 {
+    legs.iter().fold(amount as f64, |payout, (odds, share)| payout * odds * share).floor() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wager(user: u64, outcome: usize, amount: u64) -> Wager {
+        Wager { user, outcome, amount }
+    }
+
+    fn net(diffs: &[(u64, i64)]) -> i64 {
+        diffs.iter().map(|(_, diff)| diff).sum()
+    }
+
+    #[test]
+    fn split_pool_single_winner() {
+        let wagers = [wager(1, 0, 100), wager(2, 0, 300), wager(3, 1, 200)];
+        let split = split_pool(&wagers, &[(0, 1)]);
+        assert_eq!(split.diffs, vec![(1, 50), (2, 150), (3, -200)]);
+        assert_eq!(split.payouts, vec![(0, 600)]);
+    }
+
+    #[test]
+    fn split_pool_weighted_winners() {
+        let wagers = [wager(1, 0, 100), wager(2, 1, 200), wager(3, 2, 300)];
+        let split = split_pool(&wagers, &[(0, 2), (1, 1)]);
+        assert_eq!(split.payouts, vec![(0, 400), (1, 200)]);
+        assert_eq!(split.diffs, vec![(1, 300), (2, 0), (3, -300)]);
+        assert_eq!(net(&split.diffs), 0);
+    }
+
+    #[test]
+    fn split_pool_skips_winners_nobody_bet_on() {
+        let wagers = [wager(1, 0, 100), wager(2, 1, 100)];
+        let split = split_pool(&wagers, &[(0, 1), (2, 1)]);
+        assert_eq!(split.payouts, vec![(0, 200), (2, 0)]);
+        assert_eq!(split.diffs, vec![(1, 100), (2, -100)]);
+    }
+
+    #[test]
+    fn split_pool_refunds_when_no_winner_bet() {
+        let wagers = [wager(1, 0, 100), wager(2, 1, 100)];
+        let split = split_pool(&wagers, &[(2, 1)]);
+        assert_eq!(split.diffs, vec![(1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn split_pool_rounding_conserves_coins() {
+        let wagers = [wager(1, 0, 1), wager(2, 0, 1), wager(3, 0, 1), wager(4, 1, 7), wager(5, 2, 3)];
+        let split = split_pool(&wagers, &[(0, 1), (2, 1)]);
+        assert_eq!(net(&split.diffs), 0);
+        assert_eq!(split.payouts.iter().map(|(_, payout)| payout).sum::<u64>(), 13);
+        // 7 coins for the 3 equal bettors of outcome 0 can't be split evenly, nobody gets more than 1 extra coin
+        let shares: Vec<i64> = split.diffs[..3].iter().map(|(_, diff)| diff + 1).collect();
+        assert!(shares.iter().all(|share| *share == 2 || *share == 3));
+    }
+
+    fn guesses(guesses: &[(u64, f64)]) -> HashMap<u64, f64> {
+        guesses.iter().cloned().collect()
+    }
+
+    #[test]
+    fn guess_split_winner() {
+        let wagers = [wager(1, 0, 10), wager(2, 0, 20), wager(3, 0, 30)];
+        let diffs = guess_split(&wagers, &guesses(&[(1, 5.), (2, 8.), (3, 12.)]), 7.5, GuessPayout::Winner);
+        assert_eq!(diffs, vec![(1, -10), (2, 40), (3, -30)]);
+    }
+
+    #[test]
+    fn guess_split_ties_share_by_stake() {
+        let wagers = [wager(1, 0, 10), wager(2, 0, 30), wager(3, 0, 40)];
+        let diffs = guess_split(&wagers, &guesses(&[(1, 9.), (2, 11.), (3, 15.)]), 10., GuessPayout::Winner);
+        assert_eq!(diffs, vec![(1, 10), (2, 30), (3, -40)]);
+    }
+
+    #[test]
+    fn guess_split_top3() {
+        let wagers = [wager(1, 0, 10), wager(2, 0, 10), wager(3, 0, 10), wager(4, 0, 10)];
+        let diffs = guess_split(&wagers, &guesses(&[(1, 1.), (2, 2.), (3, 3.), (4, 4.)]), 0., GuessPayout::Top3);
+        assert_eq!(diffs, vec![(1, 10), (2, 2), (3, -2), (4, -10)]);
+    }
+
+    #[test]
+    fn guess_split_proportional_conserves_coins() {
+        let wagers = [wager(1, 0, 7), wager(2, 0, 13), wager(3, 0, 5), wager(4, 0, 11)];
+        let diffs = guess_split(&wagers, &guesses(&[(1, 3.3), (2, 9.), (3, 4.1), (4, 20.)]), 5., GuessPayout::Proportional);
+        assert_eq!(net(&diffs), 0);
+        // the furthest guess gets nothing back
+        assert_eq!(diffs[3], (4, -11));
+    }
+
+    #[test]
+    fn guess_split_refunds_without_guesses() {
+        let wagers = [wager(1, 0, 10), wager(2, 0, 20)];
+        for payout in GUESS_PAYOUTS {
+            assert_eq!(guess_split(&wagers, &HashMap::new(), 1., payout), vec![(1, 0), (2, 0)]);
+        }
+    }
+
+    #[test]
+    fn odds_capacity_fills_the_bankroll() {
+        assert_eq!(odds_capacity(&[], &[2., 2.], 100, 0), 100);
+        let wagers = [wager(1, 1, 50)];
+        assert_eq!(odds_capacity(&wagers, &[3., 2.], 100, 0), 75);
+    }
+
+    #[test]
+    fn odds_capacity_rounds_down() {
+        let odds = [2.5, 1.5];
+        let capacity = odds_capacity(&[], &odds, 10, 0);
+        assert_eq!(capacity, 6);
+        assert!(bankroll_covers(&[wager(1, 0, capacity)], &odds, 10));
+        assert!(!bankroll_covers(&[wager(1, 0, capacity + 1)], &odds, 10));
+    }
+
+    #[test]
+    fn odds_capacity_is_zero_when_covered_exactly() {
+        let wagers = [wager(1, 0, 100)];
+        assert!(bankroll_covers(&wagers, &[2., 2.], 100));
+        assert_eq!(odds_capacity(&wagers, &[2., 2.], 100, 0), 0);
+    }
+
+    #[test]
+    fn take_rake_from_winnings() {
+        let wagers = [wager(1, 0, 20), wager(2, 1, 60), wager(3, 0, 20)];
+        let mut received = [(1, 60), (2, 0), (3, 40)];
+        assert_eq!(take_rake(&mut received, &wagers, 10), vec![(1, 4), (3, 2)]);
+        assert_eq!(received, [(1, 56), (2, 0), (3, 38)]);
+    }
+
+    #[test]
+    fn take_rake_rounding_conserves_coins() {
+        let wagers = [wager(1, 0, 1), wager(2, 0, 1), wager(3, 0, 1), wager(4, 1, 3)];
+        let mut received = [(1, 2), (2, 2), (3, 2), (4, 0)];
+        let taken = take_rake(&mut received, &wagers, 67);
+        assert_eq!(taken.iter().map(|(_, share)| share).sum::<u64>(), 2);
+        assert!(received.iter().all(|(_, diff)| *diff == 0 || *diff == 1 || *diff == 2));
+        assert_eq!(net(&received), 4);
+    }
+
+    #[test]
+    fn take_rake_spares_refunds() {
+        let wagers = [wager(1, 0, 10), wager(2, 1, 30)];
+        let mut received = [(1, 10), (2, 30)];
+        assert!(take_rake(&mut received, &wagers, 50).is_empty());
+        assert_eq!(received, [(1, 10), (2, 30)]);
+    }
+
+    #[test]
+    fn take_rake_spares_the_bookmaker_without_winners() {
+        // nobody bet on the winning outcome, the bookmaker gets the pool and the bankroll back
+        let wagers = [wager(1, 1, 10), wager(2, 1, 30)];
+        let mut received = fixed_odds_payouts(&wagers, &[2., 1.5], 0);
+        received.push((9, 100 + 40));
+        assert!(take_rake(&mut received, &wagers, 10).is_empty());
+        assert_eq!(received.iter().map(|(_, diff)| diff).sum::<i64>(), 140);
+    }
+
+    #[test]
+    fn take_rake_spares_the_bookmaker_return() {
+        let wagers = [wager(1, 0, 10), wager(2, 1, 30)];
+        let mut received = fixed_odds_payouts(&wagers, &[3., 1.5], 0);
+        received.push((9, 100 + 40 - 30));
+        assert_eq!(take_rake(&mut received, &wagers, 10), vec![(1, 2)]);
+        assert_eq!(received, vec![(1, 28), (2, 0), (9, 110)]);
+    }
+
+    #[test]
+    fn parlay_payout_multiplies_the_legs() {
+        assert_eq!(parlay_payout(10, &[(2., 1.), (3., 1.)]), 60);
+        // a leg on one of 2 equally paid winners only counts for half
+        assert_eq!(parlay_payout(10, &[(2., 0.5), (4., 1.)]), 40);
+    }
+
+    #[test]
+    fn parlay_payout_loses_on_a_losing_leg() {
+        assert_eq!(parlay_payout(100, &[(2., 1.), (5., 0.)]), 0);
+    }
+
+    #[test]
+    fn parlay_payout_rounds_down() {
+        assert_eq!(parlay_payout(10, &[(1.55, 1.), (1.55, 1.)]), 24);
+        assert_eq!(parlay_payout(1, &[(1.9, 1.)]), 1);
+    }
+}
@@ -0,0 +1,49 @@
+use std::{sync::atomic::Ordering, time::Duration};
+use chrono::Utc;
+use log::warn;
+use serenity::http::Http;
+use crate::betting_bot::BettingBot;
+
+const TICK: Duration = Duration::from_secs(15);
+
+impl BettingBot {
+    /// Periodically performs the time based actions (such as locking bets past their deadline).
+    /// Only the first call actually runs, so it's safe to call on every `ready`.
+    pub async fn run_scheduler(&self, http: &Http) {
+        if self.scheduler_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        loop {
+            self.scheduler_tick(http).await;
+            tokio::time::sleep(TICK).await;
+        }
+    }
+
+    async fn scheduler_tick(&self, http: &Http) {
+        let now = Utc::now().timestamp();
+        match self.db.due_deadlines(now) {
+            Ok(deadlines) => for (bet_id, channel_id) in deadlines {
+                if let Err(why) = self.auto_lock(http, channel_id.into(), bet_id).await {
+                    warn!(target: "betting-bot", "Couldn't auto lock bet {}: {}", bet_id, why);
+                }
+            },
+            Err(why) => warn!(target: "betting-bot", "Couldn't fetch bet deadlines: {}", why),
+        }
+        match self.db.expired_resolutions(now) {
+            Ok(resolutions) => for (bet_id, channel_id, msg_id) in resolutions {
+                if let Err(why) = self.finalize_resolution(http, channel_id.into(), msg_id, bet_id).await {
+                    warn!(target: "betting-bot", "Couldn't finalize the resolution of bet {}: {}", bet_id, why);
+                }
+            },
+            Err(why) => warn!(target: "betting-bot", "Couldn't fetch expired resolutions: {}", why),
+        }
+        match self.db.expired_challenges(now) {
+            Ok(challenges) => for (challenge_id, channel_id) in challenges {
+                if let Err(why) = self.expire_challenge(http, channel_id.into(), challenge_id).await {
+                    warn!(target: "betting-bot", "Couldn't expire challenge {}: {}", challenge_id, why);
+                }
+            },
+            Err(why) => warn!(target: "betting-bot", "Couldn't fetch expired challenges: {}", why),
+        }
+    }
+}
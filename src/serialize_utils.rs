@@ -8,6 +8,28 @@ pub const BET_CLICK: &str = "bet_click";
 pub const RESOLVE: &str = "resolve";
 pub const ABORT: &str = "abort";
 pub const BET_ORDER: &str = "bet_order";
+pub const QUICK_BET: &str = "quick_bet";
+pub const SPLIT_SELECT: &str = "split_select";
+pub const SPLIT_ORDER: &str = "split_order";
+pub const UNDO: &str = "undo";
+pub const HISTORY: &str = "history";
+pub const AUDIT: &str = "audit";
+pub const NUMBER_RESULT: &str = "number_result";
+pub const NUMBER_ORDER: &str = "number_order";
+pub const WITHDRAW: &str = "withdraw";
+pub const WITHDRAW_ORDER: &str = "withdraw_order";
+pub const SWITCH: &str = "switch";
+pub const PARLAY_ORDER: &str = "parlay_order";
+pub const BET_FORM: &str = "bet_form";
+pub const EDIT: &str = "edit";
+pub const EDIT_ORDER: &str = "edit_order";
+pub const CHALLENGE_ACCEPT: &str = "challenge_accept";
+pub const CHALLENGE_DECLINE: &str = "challenge_decline";
+pub const CHALLENGE_VOTE: &str = "challenge_vote";
+pub const CHALLENGE_ARBITRATE: &str = "challenge_arbitrate";
+pub const PICK_OUTCOME: &str = "pick_outcome";
+pub const SWITCH_PICK: &str = "switch_pick";
+pub const RESOLVE_PICK: &str = "resolve_pick";
 
 #[derive(Debug)]
 pub enum BetAction {
@@ -15,7 +37,48 @@ pub enum BetAction {
     Abort,
     BetClick(BetOutcome),
     Resolve(BetOutcome),
-    BetOrder
+    BetOrder,
+    /// Bets a percentage of the user's balance without going through the modal
+    QuickBet(BetOutcome, u8),
+    /// Selection of several winning outcomes of a bet
+    SplitSelect(u64),
+    /// Weights of the winning outcomes of a bet
+    SplitOrder(u64),
+    /// Reverts the resolution of a bet
+    Undo(u64),
+    /// Page of the history of a user
+    History(u64, usize),
+    /// Page of the audit log of the guild
+    Audit(usize),
+    /// Asks for the actual result of an over/under or closest guess bet
+    NumberResult(u64),
+    /// Actual result of an over/under or closest guess bet
+    NumberOrder(u64),
+    /// Asks how much of their position the user wants to take back from an open bet
+    Withdraw(u64),
+    /// Amount taken back from an open bet
+    WithdrawOrder(u64),
+    /// Moves the position of the user to this outcome
+    Switch(BetOutcome),
+    /// Legs picked for a parlay of this amount
+    ParlayOrder(u64),
+    /// Bet creation form, with the closing delay, min stake, max stake and max pool share given to the command
+    BetForm(Option<i64>, Option<u64>, Option<u64>, Option<u32>),
+    Edit,
+    /// New description and outcomes of an open bet
+    EditOrder(u64),
+    ChallengeAccept,
+    ChallengeDecline,
+    /// One side of a challenge says this user won
+    ChallengeVote(u64),
+    /// An admin settles a disputed challenge in favor of this user, 0 to refund both sides
+    ChallengeArbitrate(u64),
+    /// Menu of the outcomes of a compact bet to bet on
+    PickOutcome(u64),
+    /// Menu of the outcomes of a compact bet to move one's position to
+    SwitchPick(u64),
+    /// Menu of the outcomes of a locked compact bet to pick the winner
+    ResolvePick(u64),
 }
 
 impl Display for BetAction {
@@ -25,11 +88,45 @@ impl Display for BetAction {
             BetAction::BetClick(bet_outcome) => format!("{}-{}", BET_CLICK, bet_outcome),
             BetAction::Resolve(bet_outcome) => format!("{}-{}", RESOLVE, bet_outcome),
             BetAction::Abort => format!("{}-", ABORT),
-            BetAction::BetOrder => format!("{}-", BET_ORDER)
+            BetAction::BetOrder => format!("{}-", BET_ORDER),
+            BetAction::QuickBet(bet_outcome, percent) => format!("{}-{}-{}", QUICK_BET, percent, bet_outcome),
+            BetAction::SplitSelect(bet_id) => format!("{}-{}", SPLIT_SELECT, bet_id),
+            BetAction::SplitOrder(bet_id) => format!("{}-{}", SPLIT_ORDER, bet_id),
+            BetAction::Undo(bet_id) => format!("{}-{}", UNDO, bet_id),
+            BetAction::History(user, page) => format!("{}-{}-{}", HISTORY, user, page),
+            BetAction::Audit(page) => format!("{}-{}", AUDIT, page),
+            BetAction::NumberResult(bet_id) => format!("{}-{}", NUMBER_RESULT, bet_id),
+            BetAction::NumberOrder(bet_id) => format!("{}-{}", NUMBER_ORDER, bet_id),
+            BetAction::Withdraw(bet_id) => format!("{}-{}", WITHDRAW, bet_id),
+            BetAction::WithdrawOrder(bet_id) => format!("{}-{}", WITHDRAW_ORDER, bet_id),
+            BetAction::Switch(bet_outcome) => format!("{}-{}", SWITCH, bet_outcome),
+            BetAction::ParlayOrder(amount) => format!("{}-{}", PARLAY_ORDER, amount),
+            BetAction::BetForm(closes_in, min_stake, max_stake, max_pool_share) => format!(
+                "{}-{}-{}-{}-{}", BET_FORM, optional(closes_in), optional(min_stake), 
+                optional(max_stake), optional(max_pool_share)
+            ),
+            BetAction::Edit => format!("{}-", EDIT),
+            BetAction::EditOrder(bet_id) => format!("{}-{}", EDIT_ORDER, bet_id),
+            BetAction::ChallengeAccept => format!("{}-", CHALLENGE_ACCEPT),
+            BetAction::ChallengeDecline => format!("{}-", CHALLENGE_DECLINE),
+            BetAction::ChallengeVote(winner) => format!("{}-{}", CHALLENGE_VOTE, winner),
+            BetAction::ChallengeArbitrate(winner) => format!("{}-{}", CHALLENGE_ARBITRATE, winner),
+            BetAction::PickOutcome(bet_id) => format!("{}-{}", PICK_OUTCOME, bet_id),
+            BetAction::SwitchPick(bet_id) => format!("{}-{}", SWITCH_PICK, bet_id),
+            BetAction::ResolvePick(bet_id) => format!("{}-{}", RESOLVE_PICK, bet_id),
         })
     }
 }
 
+/// Optional values of a custom id are left empty when missing
+fn optional<T: Display>(value: &Option<T>) -> String {
+    value.as_ref().map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn parse_optional<T: std::str::FromStr>(value: &str) -> anyhow::Result<Option<T>> where T::Err: std::error::Error + Send + Sync + 'static {
+    if value.is_empty() { Ok(None) } else { Ok(Some(value.parse()?)) }
+}
+
 impl From<BetAction> for String {
     fn from(value: BetAction) -> Self {
         value.to_string()
@@ -49,6 +146,45 @@ impl TryFrom<String> for BetAction {
             RESOLVE => BetAction::Resolve(BetOutcome::try_from(data)?),
             ABORT => BetAction::Abort,
             BET_ORDER => BetAction::BetOrder,
+            QUICK_BET => {
+                let (percent, bet_outcome) = data.split_once("-").ok_or(
+                    anyhow!("'{}' is not a quick bet. Expecting <percent>-<bet_outcome>", data)
+                )?;
+                BetAction::QuickBet(BetOutcome::try_from(bet_outcome)?, percent.parse()?)
+            },
+            SPLIT_SELECT => BetAction::SplitSelect(data.parse()?),
+            SPLIT_ORDER => BetAction::SplitOrder(data.parse()?),
+            UNDO => BetAction::Undo(data.parse()?),
+            HISTORY => {
+                let (user, page) = data.split_once("-").ok_or(
+                    anyhow!("'{}' is not a history page. Expecting <user>-<page>", data)
+                )?;
+                BetAction::History(user.parse()?, page.parse()?)
+            },
+            AUDIT => BetAction::Audit(data.parse()?),
+            NUMBER_RESULT => BetAction::NumberResult(data.parse()?),
+            NUMBER_ORDER => BetAction::NumberOrder(data.parse()?),
+            WITHDRAW => BetAction::Withdraw(data.parse()?),
+            WITHDRAW_ORDER => BetAction::WithdrawOrder(data.parse()?),
+            SWITCH => BetAction::Switch(BetOutcome::try_from(data)?),
+            PARLAY_ORDER => BetAction::ParlayOrder(data.parse()?),
+            BET_FORM => {
+                let (closes_in, min_stake, max_stake, max_pool_share) = data.split("-").collect_tuple().ok_or(
+                    anyhow!("'{}' is not a bet form. Expecting <closes_in>-<min_stake>-<max_stake>-<max_pool_share>", data)
+                )?;
+                BetAction::BetForm(
+                    parse_optional(closes_in)?, parse_optional(min_stake)?, parse_optional(max_stake)?, parse_optional(max_pool_share)?
+                )
+            },
+            EDIT => BetAction::Edit,
+            EDIT_ORDER => BetAction::EditOrder(data.parse()?),
+            CHALLENGE_ACCEPT => BetAction::ChallengeAccept,
+            CHALLENGE_DECLINE => BetAction::ChallengeDecline,
+            CHALLENGE_VOTE => BetAction::ChallengeVote(data.parse()?),
+            CHALLENGE_ARBITRATE => BetAction::ChallengeArbitrate(data.parse()?),
+            PICK_OUTCOME => BetAction::PickOutcome(data.parse()?),
+            SWITCH_PICK => BetAction::SwitchPick(data.parse()?),
+            RESOLVE_PICK => BetAction::ResolvePick(data.parse()?),
             _ => bail!("Bet action '{}' not recognized", action)
         })
     }
@@ -82,3 +218,62 @@ impl ToSql for BetOutcome {
         Ok(ToSqlOutput::Owned(Value::Text(self.to_string())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bet_action_round_trip() {
+        let outcome = || BetOutcome { bet_id: 1234567890123, outcome_id: 3 };
+        let actions = vec![
+            BetAction::Lock,
+            BetAction::Abort,
+            BetAction::BetClick(outcome()),
+            BetAction::Resolve(outcome()),
+            BetAction::BetOrder,
+            BetAction::QuickBet(outcome(), 50),
+            BetAction::SplitSelect(42),
+            BetAction::SplitOrder(42),
+            BetAction::Undo(42),
+            BetAction::History(7, 2),
+            BetAction::Audit(3),
+            BetAction::NumberResult(42),
+            BetAction::NumberOrder(42),
+            BetAction::Withdraw(42),
+            BetAction::WithdrawOrder(42),
+            BetAction::Switch(outcome()),
+            BetAction::ParlayOrder(100),
+            BetAction::BetForm(Some(30), Some(10), None, Some(50)),
+            BetAction::BetForm(None, None, None, None),
+            BetAction::Edit,
+            BetAction::EditOrder(42),
+            BetAction::ChallengeAccept,
+            BetAction::ChallengeDecline,
+            BetAction::ChallengeVote(7),
+            BetAction::ChallengeArbitrate(0),
+            BetAction::PickOutcome(42),
+            BetAction::SwitchPick(42),
+            BetAction::ResolvePick(42),
+        ];
+        for action in actions {
+            let custom_id = action.to_string();
+            let parsed = BetAction::try_from(custom_id.clone()).unwrap();
+            assert_eq!(parsed.to_string(), custom_id);
+            assert_eq!(format!("{:?}", parsed), format!("{:?}", action));
+        }
+    }
+
+    #[test]
+    fn bet_outcome_round_trip() {
+        let bet_outcome = BetOutcome::try_from("1234567890123-12").unwrap();
+        assert_eq!((bet_outcome.bet_id, bet_outcome.outcome_id), (1234567890123, 12));
+        assert_eq!(bet_outcome.to_string(), "1234567890123-12");
+    }
+
+    #[test]
+    fn unknown_action_is_rejected() {
+        assert!(BetAction::try_from("nope-1".to_string()).is_err());
+        assert!(BetAction::try_from("lock".to_string()).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use std::cmp::max;
+
+pub struct UserStats {
+    pub bets: u64,
+    pub resolved: u64,
+    pub wins: u64,
+    pub staked: u64,
+    pub profit: i64,
+    pub biggest_payout: u64,
+    pub longest_streak: u64,
+    pub favourite_author: Option<(u64, u64)>,
+}
+
+impl UserStats {
+    /// `settled` are the (stake, payout) of the resolved positions, oldest first
+    pub fn new(bets: u64, settled: &[(u64, u64)], favourite_author: Option<(u64, u64)>) -> Self {
+        let mut stats = UserStats {
+            bets, resolved: settled.len() as u64, wins: 0, staked: 0, profit: 0, 
+            biggest_payout: 0, longest_streak: 0, favourite_author
+        };
+        let mut streak = 0;
+        for (stake, payout) in settled {
+            stats.staked += stake;
+            stats.profit += *payout as i64 - *stake as i64;
+            stats.biggest_payout = max(stats.biggest_payout, *payout);
+            if *payout > 0 {
+                stats.wins += 1;
+                streak += 1;
+                stats.longest_streak = max(stats.longest_streak, streak);
+            } else {
+                streak = 0;
+            }
+        }
+        stats
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        self.wins as f64 / self.resolved as f64
+    }
+
+    pub fn roi(&self) -> f64 {
+        self.profit as f64 / self.staked as f64
+    }
+}